@@ -1,27 +1,75 @@
-use bevy::{color::Color, ui::Val};
+use bevy::{color::Color, math::UVec2, ui::Val};
 
 pub const TILE_SIZE: f32 = 240.0;
+// Size of one frame in the spritesheets, in pixels
+pub const SPRITE_FRAME_SIZE: UVec2 = UVec2::splat(TILE_SIZE as u32);
 pub const TILE_SCALE: f32 = 0.5;
 pub const SCALED_TILE_SIZE: f32 = TILE_SIZE * TILE_SCALE;
 pub const GROUND_Y: f32 = -400.0; // Bottom of the screen
 pub const WINDOW_WIDTH: f32 = 1200.;
 pub const WINDOW_HEIGHT: f32 = 800.;
 pub const INITIAL_VELOCITY: f32 = 200.;
-pub const COLLISION_RADIUS: f32 = 60.;
+pub const VELOCITY_ACCELERATION: f32 = 0.02; // Velocity gained per distance travelled
+pub const MAXIMUM_VELOCITY: f32 = 600.;
+pub const RESTART_LOCK_SECS: f32 = 0.75;
+pub const REPLAY_RESTART_SECS: f32 = 3.;
+pub const AUTOPILOT_RESTART_SECS: f32 = 3.;
+pub const AUTOPILOT_LOOKAHEAD_JUMPS: f32 = 2.; // Jump lengths ahead the autopilot plans for
 
 pub const PLAYER_X: f32 = -600.0 + (WINDOW_WIDTH * 0.20);
 pub const PLAYER_GROUND: f32 = GROUND_Y + (WINDOW_HEIGHT * 0.33);
+pub const JUMP_VELOCITY: f32 = 500.;
+pub const JUMP_GRAVITY: f32 = -980.;
+pub const JUMP_RELEASE_VELOCITY: f32 = 300.; // Upward velocity left when the button is released early
+pub const JUMP_BUFFER_SECS: f32 = 0.12; // A press this long before landing jumps again on landing
+pub const JUMP_COYOTE_SECS: f32 = 0.1; // The player can still jump this long after losing the ground
+pub const FIXED_TIMESTEP: f32 = 1. / 64.; // Bevy's default fixed update rate
+pub const HEADLESS_MAX_TICKS: u64 = 64 * 60 * 60; // An hour of play
 pub const ENEMY_WALKING_Y: f32 = PLAYER_GROUND;
-pub const ENEMY_FLYING_Y: f32 = ENEMY_WALKING_Y + 150.;
+pub const ENEMY_FLYING_Y: f32 = ENEMY_WALKING_Y + 55.; // Low enough to hit a running player, high enough to duck under
 pub const ENEMY_MINIMUM_SPACE: f32 = 500.;
 pub const ENEMY_MAXIMUM_SPACE: f32 = 1000.;
 pub const ENEMY_INITIAL_X: f32 = 500.;
+pub const ENEMY_GENERATION_ATTEMPTS: usize = 16;
+pub const ENEMY_DEFINITIONS_FILE: &str = "enemies/default.enemies.ron";
+pub const ENEMY_SPAWN_TABLE_FILE: &str = "enemies/normal.spawns.ron";
+pub const POWERUP_MINIMUM_SPACE: f32 = 3000.;
+pub const POWERUP_MAXIMUM_SPACE: f32 = 6000.;
+pub const POWERUP_Y: f32 = PLAYER_GROUND + 30.; // Collected running or jumping, but not ducking
+pub const POWERUP_SIZE: f32 = 30.;
+pub const POWERUP_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+pub const DOUBLE_JUMP_SECS: f32 = 10.;
+pub const WATER_CHANCE: f64 = 0.25; // Chance of a tile being water where water is allowed
+pub const WATER_SINK_DEPTH: f32 = 30.; // How far a drowned player sinks into the water
 
 pub const SCOREBOARD_FONT_SIZE: f32 = 33.;
 pub const TEXT_COLOR: Color = Color::srgb(0.5, 0.5, 1.);
 pub const SCOREBOARD_TEXT_PADDING: Val = Val::Px(5.0);
 pub const SCORE_COLOR: Color = Color::srgb(1.0, 0.5, 0.5);
+pub const HUD_FONT_SIZE: f32 = 24.;
+pub const HUD_TOP: Val = Val::Px(45.0);
 
 pub const SIGN_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
 pub const SIGN_FONT_SIZE: f32 = 33.;
-pub const SIGN_OFFSET_Y: f32 = 33.;
\ No newline at end of file
+pub const SIGN_OFFSET_Y: f32 = 33.;
+
+pub const SCREEN_TITLE_FONT_SIZE: f32 = 80.;
+pub const SCREEN_TEXT_FONT_SIZE: f32 = 33.;
+pub const SCREEN_TITLE_COLOR: Color = Color::srgb(1.0, 0.5, 0.5);
+pub const SCREEN_TEXT_COLOR: Color = Color::srgb(0.5, 0.5, 1.);
+pub const SCREEN_ROW_GAP: Val = Val::Px(20.0);
+pub const SCREEN_OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.4);
+pub const BUTTON_COLOR: Color = Color::srgb(0.2, 0.2, 0.3);
+pub const BUTTON_HOVERED_COLOR: Color = Color::srgb(0.3, 0.3, 0.45);
+pub const BUTTON_PADDING: Val = Val::Px(10.0);
+pub const BUTTON_WIDTH: Val = Val::Px(200.0);
+
+pub const HIGHSCORE_COUNT: usize = 5;
+pub const HIGHSCORE_DIRECTORY: &str = "capy_run";
+pub const HIGHSCORE_FILE: &str = "highscores.ron";
+pub const DAILY_BEST_FILE: &str = "daily_bests.ron";
+pub const CONFIG_DIRECTORY: &str = "capy_run";
+pub const BINDINGS_FILE: &str = "bindings.ron";
+pub const HIGHSCORE_FONT_SIZE: f32 = 24.;
+
+pub const HITBOX_COLOR: Color = Color::srgb(1.0, 0.0, 0.0);
@@ -0,0 +1,137 @@
+//! Enemy definitions
+//!
+//! Enemies are described in `assets/enemies/default.enemies.ron`, a new enemy only needs an
+//! entry there, a spritesheet and a weight in the spawn tables. The file is read by an asset
+//! loader, also when running headless, so every run is generated from the same definitions.
+
+use std::io::{Error, ErrorKind};
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::constants::{ENEMY_FLYING_Y, ENEMY_WALKING_Y};
+use crate::hitbox::{Aabb, Hitbox};
+use crate::plugin_sprite_animation::Playback;
+
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum EnemyLane {
+    Walking,
+    Flying,
+}
+
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct EnemyDefinition {
+    pub name: String,
+    pub spritesheet: String,
+    // Size of one frame in the spritesheet, in pixels, the frames are counted from the image
+    pub frame_size: UVec2,
+    pub fps: u8,
+    // Loops unless given
+    #[serde(default)]
+    pub playback: Playback,
+    pub lane: EnemyLane,
+    pub hitbox: Vec<Aabb>,
+}
+
+
+impl EnemyDefinition {
+    pub fn lane_y(&self) -> f32 {
+        match self.lane {
+            EnemyLane::Walking => ENEMY_WALKING_Y,
+            EnemyLane::Flying => ENEMY_FLYING_Y,
+        }
+    }
+
+    pub fn hitbox(&self) -> Hitbox {
+        Hitbox::new(&self.hitbox)
+    }
+}
+
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct EnemyDefinitions(pub Vec<EnemyDefinition>);
+
+
+impl EnemyDefinitions {
+    pub fn parse(bytes: &[u8]) -> Result<Self, BevyError> {
+        let definitions: Self = ron::de::from_bytes(bytes)?;
+        definitions.validate()?;
+        Ok(definitions)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |message: String| Err(Error::new(ErrorKind::InvalidData, message));
+
+        for definition in &self.0 {
+            if definition.frame_size.min_element() == 0 || definition.fps == 0 {
+                return invalid(format!("{} needs a frame size and a frame rate", definition.name));
+            }
+            if definition.hitbox.is_empty() {
+                return invalid(format!("{} has no hitbox", definition.name));
+            }
+            if self.0.iter().filter(|other| other.name == definition.name).count() > 1 {
+                return invalid(format!("{} is defined more than once", definition.name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+#[derive(Default)]
+pub struct EnemyDefinitionsLoader;
+
+
+impl AssetLoader for EnemyDefinitionsLoader {
+    type Asset = EnemyDefinitions;
+    type Settings = ();
+    type Error = BevyError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<EnemyDefinitions, BevyError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        EnemyDefinitions::parse(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["enemies.ron"]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_definitions_are_valid() {
+        let definitions = EnemyDefinitions::parse(include_bytes!("../assets/enemies/default.enemies.ron")).unwrap();
+        let names: Vec<&str> = definitions.0.iter().map(|definition| definition.name.as_str()).collect();
+
+        assert_eq!(names, ["Eagle", "Lion", "Crocodile"]);
+    }
+
+    #[test]
+    fn enemies_are_told_apart_by_name() {
+        let lion = r#"(
+            name: "Lion",
+            spritesheet: "textures/enemies/lion/running.png",
+            frame_size: (240, 240),
+            fps: 1,
+            lane: Walking,
+            hitbox: [(offset: (0.0, 0.0), half_size: (20.0, 10.0))],
+        )"#;
+
+        assert!(EnemyDefinitions::parse(format!("[{}]", lion).as_bytes()).is_ok());
+        assert!(EnemyDefinitions::parse(format!("[{}, {}]", lion, lion).as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,478 @@
+//! Headless simulation of the game logic
+//!
+//! The game runs without a window and rendering on top of `MinimalPlugins`, only the enemy
+//! definitions are loaded from the assets. Time is advanced by hand, every update of the app
+//! plays exactly one fixed update tick, so runs play out the same on every machine.
+
+use std::time::Duration;
+use bevy::asset::AssetPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::constants::FIXED_TIMESTEP;
+use crate::model::{GameData, GameState, Model, SeedMode};
+use crate::plugin_enemy::EnemyPlugin;
+use crate::plugin_game_controller::GameController;
+use crate::plugin_ground::GroundPlugin;
+use crate::plugin_player::PlayerPlugin;
+use crate::plugin_powerup::PowerUpPlugin;
+use crate::plugin_signs::SignPlugin;
+
+
+// Builds an app with the game logic only, inputs have to be added by the caller
+pub fn headless_app(seed_mode: SeedMode) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()))
+       .insert_resource(Time::<Fixed>::from_seconds(FIXED_TIMESTEP as f64))
+       .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(FIXED_TIMESTEP)))
+       .add_plugins(Model { seed_mode })
+       .add_plugins(GroundPlugin)
+       .add_plugins(SignPlugin)
+       .add_plugins(PlayerPlugin)
+       .add_plugins(EnemyPlugin)
+       .add_plugins(PowerUpPlugin)
+       .add_plugins(GameController);
+
+    app
+}
+
+
+// Plays until the player died and returns the distance of the run,
+// None if the run is still going after the given number of ticks or the app exited
+pub fn run_until_dead(app: &mut App, max_ticks: u64) -> Option<f32> {
+    for _ in 0..max_ticks {
+        app.update();
+
+        if app.should_exit().is_some() {
+            return None;
+        }
+
+        if *app.world().resource::<State<GameState>>().get() == GameState::Dead {
+            return Some(app.world().resource::<GameData>().current_score);
+        }
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::ButtonState;
+    use bevy::input::InputPlugin;
+    use bevy::input::mouse::MouseButtonInput;
+    use bevy::input::touch::{TouchInput, TouchPhase};
+    use bevy::window::PrimaryWindow;
+    use crate::constants::PLAYER_GROUND;
+    use crate::model::{DifficultyCurve, GameEnd, GamePause, GameReset, GameResume, GameStart, GameSystems, KilledBy, PlayerDuck, PlayerJump, PlayerJumpReleased, StateTicks};
+    use crate::plugin_actions::{ActionsPlugin, Bindings};
+    use crate::plugin_autopilot::AutopilotPlugin;
+    use crate::plugin_game_controller::RestartLock;
+    use crate::plugin_pointer_input::PointerInputPlugin;
+    use crate::constants::DOUBLE_JUMP_SECS;
+    use crate::plugin_player::{Jump, PlayerSprite};
+    use crate::plugin_replay::{Replay, ReplayMode, ReplayPlugin};
+
+    // About three minutes of play
+    const MAX_TICKS: u64 = 64 * 180;
+
+    // Starts the run right away and jumps every given number of ticks, never if zero
+    fn add_scripted_input(app: &mut App, jump_interval: u64) {
+        app.add_systems(FixedUpdate, (move |mut commands: Commands, state: Res<State<GameState>>, ticks: Res<StateTicks>| {
+            match state.get() {
+                GameState::WaitingForStart => commands.trigger(GameStart),
+                GameState::Running if jump_interval > 0 && ticks.0.is_multiple_of(jump_interval) => commands.trigger(PlayerJump),
+                _ => {}
+            }
+        }).in_set(GameSystems::Input));
+    }
+
+    // Plays with the mouse through the default bindings, every update of the app is a frame
+    // running the given number of ticks
+    fn pointer_app(ticks_per_frame: f32) -> App {
+        let mut app = headless_app(SeedMode::Fixed(0));
+        app.insert_resource(Bindings::default())
+           .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(FIXED_TIMESTEP * ticks_per_frame)))
+           .add_plugins((InputPlugin, ActionsPlugin, PointerInputPlugin));
+        app
+    }
+
+    // Presses or releases the left mouse button for the next frame
+    fn click(app: &mut App, state: ButtonState) {
+        app.world_mut().write_message(MouseButtonInput { button: MouseButton::Left, state, window: Entity::PLACEHOLDER });
+        app.update();
+    }
+
+    // Clicks to start once the enemies are loaded and plays until the run is under way
+    fn start_with_click(app: &mut App) {
+        while *app.world().resource::<State<GameState>>().get() != GameState::WaitingForStart {
+            app.update();
+        }
+
+        click(app, ButtonState::Pressed);
+        click(app, ButtonState::Released);
+
+        while *app.world().resource::<State<GameState>>().get() != GameState::Running {
+            app.update();
+        }
+        for _ in 0..8 {
+            app.update();
+        }
+    }
+
+    fn run_with_jumps(seed: u64, jump_interval: u64) -> Option<f32> {
+        let mut app = headless_app(SeedMode::Fixed(seed));
+        add_scripted_input(&mut app, jump_interval);
+        run_until_dead(&mut app, MAX_TICKS)
+    }
+
+    // Height of the player in every running tick, pressing and releasing jump at the given ticks
+    fn jump_heights(presses: Vec<u64>, releases: Vec<u64>, double_jump: bool) -> Vec<f32> {
+        let mut app = headless_app(SeedMode::Fixed(0));
+        if double_jump {
+            app.add_systems(OnEnter(GameState::Running), |mut jump: Single<&mut Jump>| jump.double_jump = DOUBLE_JUMP_SECS);
+        }
+        app.add_systems(FixedUpdate, (move |mut commands: Commands, state: Res<State<GameState>>, ticks: Res<StateTicks>| {
+            match state.get() {
+                GameState::WaitingForStart => commands.trigger(GameStart),
+                GameState::Running if presses.contains(&ticks.0) => commands.trigger(PlayerJump),
+                GameState::Running if releases.contains(&ticks.0) => commands.trigger(PlayerJumpReleased),
+                _ => {}
+            }
+        }).in_set(GameSystems::Input));
+
+        let mut heights = vec![0.0];
+        while heights.len() < 160 {
+            app.update();
+            if *app.world().resource::<State<GameState>>().get() != GameState::Running {
+                continue;
+            }
+
+            let mut players = app.world_mut().query_filtered::<&Transform, With<PlayerSprite>>();
+            let player = players.single(app.world()).unwrap();
+            heights.push(player.translation.y - PLAYER_GROUND);
+        }
+        heights
+    }
+
+    fn highest(heights: &[f32]) -> f32 {
+        heights.iter().copied().fold(f32::MIN, f32::max)
+    }
+
+    #[derive(Resource, Default)]
+    struct Jumps(u32);
+
+    #[test]
+    fn press_in_a_frame_of_two_ticks_jumps_once() {
+        let mut app = pointer_app(2.0);
+        app.init_resource::<Jumps>()
+           .add_observer(|_jump: On<PlayerJump>, mut jumps: ResMut<Jumps>| jumps.0 += 1);
+        start_with_click(&mut app);
+
+        click(&mut app, ButtonState::Pressed);
+        for _ in 0..4 {
+            app.update();
+        }
+
+        assert_eq!(app.world().resource::<Jumps>().0, 1);
+    }
+
+    #[test]
+    fn releasing_jump_early_jumps_lower() {
+        let held = highest(&jump_heights(vec![2], vec![], false));
+        let tapped = highest(&jump_heights(vec![2], vec![4], false));
+
+        assert!(tapped > 0.0);
+        assert!(tapped < held * 0.8, "tapped {} held {}", tapped, held);
+    }
+
+    // Highest point of a jump clicked and held for the given number of frames, the frames
+    // only run half a tick so every other one runs none
+    fn clicked_jump_height(held_frames: usize) -> f32 {
+        let mut app = pointer_app(0.5);
+        start_with_click(&mut app);
+
+        click(&mut app, ButtonState::Pressed);
+        for _ in 0..held_frames {
+            app.update();
+        }
+        click(&mut app, ButtonState::Released);
+
+        let mut players = app.world_mut().query_filtered::<&Transform, With<PlayerSprite>>();
+        let heights: Vec<f32> = (0..200).map(|_| {
+            app.update();
+            players.single(app.world()).unwrap().translation.y - PLAYER_GROUND
+        }).collect();
+        highest(&heights)
+    }
+
+    #[test]
+    fn clicking_shortly_jumps_lower_than_holding() {
+        let held = clicked_jump_height(60);
+
+        for held_frames in [0, 1] {
+            let tapped = clicked_jump_height(held_frames);
+
+            assert!(tapped > 0.0, "the tap did not jump");
+            assert!(tapped < held * 0.8, "tapped {} held {}", tapped, held);
+        }
+    }
+
+    // Starts or ends a touch of one finger for the next frame
+    fn touch(app: &mut App, id: u64, phase: TouchPhase, position: Vec2) {
+        app.world_mut().write_message(TouchInput { phase, position, window: Entity::PLACEHOLDER, force: None, id });
+        app.update();
+    }
+
+    #[derive(Resource, Default)]
+    struct Gestures {
+        ducks: usize,
+        jumps: usize,
+        pauses: usize,
+    }
+
+    #[test]
+    fn touch_gestures_duck_jump_and_pause() {
+        let mut app = pointer_app(0.5);
+        app.world_mut().spawn((Window::default(), PrimaryWindow));
+        app.init_resource::<Gestures>()
+           .add_observer(|_: On<PlayerDuck>, mut gestures: ResMut<Gestures>| gestures.ducks += 1)
+           .add_observer(|_: On<PlayerJump>, mut gestures: ResMut<Gestures>| gestures.jumps += 1)
+           .add_observer(|_: On<GamePause>, mut gestures: ResMut<Gestures>| gestures.pauses += 1);
+        start_with_click(&mut app);
+
+        let height = Window::default().height();
+        let lower = Vec2::new(100., height * 0.75);
+        let upper = Vec2::new(100., height * 0.25);
+        let counts = |app: &App| {
+            let gestures = app.world().resource::<Gestures>();
+            (gestures.ducks, gestures.jumps, gestures.pauses)
+        };
+
+        touch(&mut app, 1, TouchPhase::Started, lower);
+        touch(&mut app, 1, TouchPhase::Ended, lower);
+        assert_eq!(counts(&app), (1, 0, 0), "a touch on the lower half ducks");
+
+        touch(&mut app, 2, TouchPhase::Started, upper);
+        touch(&mut app, 2, TouchPhase::Ended, upper);
+        assert_eq!(counts(&app), (1, 1, 0), "a tap on the upper half jumps");
+
+        touch(&mut app, 3, TouchPhase::Started, upper);
+        touch(&mut app, 4, TouchPhase::Started, lower);
+        app.update();
+        assert_eq!(counts(&app), (2, 2, 1), "a second finger pauses");
+        app.update();
+        assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Paused);
+    }
+
+    #[test]
+    fn press_right_before_landing_jumps_again() {
+        let single = jump_heights(vec![2], vec![], false);
+        let landing = single.iter().skip(3).position(|&height| height == 0.0).unwrap() + 3;
+
+        let buffered = jump_heights(vec![2, landing as u64 - 3], vec![], false);
+        let too_early = jump_heights(vec![2, 10], vec![], false);
+
+        assert!(buffered[landing + 5] > 0.0);
+        assert_eq!(too_early[landing + 5], 0.0);
+    }
+
+    #[test]
+    fn power_up_allows_a_second_jump_in_the_air() {
+        let single = highest(&jump_heights(vec![2, 20], vec![10], false));
+        let double = highest(&jump_heights(vec![2, 20], vec![10], true));
+
+        assert!(double > single * 1.3, "double {} single {}", double, single);
+    }
+
+    #[test]
+    fn second_jump_needs_a_new_press() {
+        let single = highest(&jump_heights(vec![2, 20], vec![10], false));
+        let held = highest(&jump_heights(vec![2, 20], vec![], true));
+        let repeated = highest(&jump_heights(vec![2, 3, 20], vec![10], true));
+
+        assert!(held < single * 1.3, "held {} single {}", held, single);
+        assert!(repeated > single * 1.3, "the repeated press used up the second jump, {} single {}", repeated, single);
+    }
+
+    #[test]
+    fn power_up_is_collected_on_the_way() {
+        let mut app = headless_app(SeedMode::Fixed(5));
+        app.add_plugins(AutopilotPlugin);
+
+        let collected = (0..MAX_TICKS).any(|_| {
+            app.update();
+            app.world_mut().query::<&Jump>().single(app.world()).unwrap().double_jump > 0.0
+        });
+
+        assert!(collected);
+    }
+
+    #[test]
+    fn pause_freezes_the_run_mid_jump() {
+        let mut app = headless_app(SeedMode::Fixed(0));
+        add_scripted_input(&mut app, 20);
+        let snapshot = |app: &mut App| {
+            let mut players = app.world_mut().query_filtered::<&Transform, With<PlayerSprite>>();
+            let height = players.single(app.world()).unwrap().translation.y;
+            (app.world().resource::<GameData>().current_score, app.world().resource::<StateTicks>().0, height)
+        };
+
+        while app.world().resource::<StateTicks>().0 < 30 || *app.world().resource::<State<GameState>>().get() != GameState::Running {
+            app.update();
+        }
+        app.world_mut().trigger(GamePause);
+        app.update();
+        let paused = snapshot(&mut app);
+        assert!(paused.2 > PLAYER_GROUND, "the player should be in the air");
+
+        for _ in 0..64 {
+            app.update();
+        }
+        assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Paused);
+        assert_eq!(snapshot(&mut app), paused);
+
+        app.world_mut().trigger(GameResume);
+        app.update();
+        app.update();
+        let resumed = snapshot(&mut app);
+        assert!(resumed.0 > paused.0);
+        assert!(resumed.1 > paused.1 && resumed.1 < paused.1 + 4, "the ticks continue after a pause");
+    }
+
+    #[test]
+    fn restart_while_still_falling_starts_on_the_ground() {
+        let mut app = headless_app(SeedMode::Fixed(0));
+        add_scripted_input(&mut app, 0);
+        while *app.world().resource::<State<GameState>>().get() != GameState::Running {
+            app.update();
+        }
+
+        app.world_mut().trigger(PlayerJump);
+        for _ in 0..5 {
+            app.update();
+        }
+        app.world_mut().trigger(GameEnd { killed_by: KilledBy::Water });
+
+        // Restarts as soon as the restart lock allows it
+        while !app.world().resource::<RestartLock>().0.is_finished() {
+            app.update();
+        }
+        assert_ne!(app.world_mut().query::<&Jump>().single(app.world()).unwrap().velocity, 0.0, "the player should be in the air");
+
+        app.world_mut().trigger(GameReset);
+        while *app.world().resource::<State<GameState>>().get() != GameState::Running {
+            app.update();
+        }
+
+        let mut players = app.world_mut().query_filtered::<(&Transform, &Jump), With<PlayerSprite>>();
+        let (transform, jump) = players.single(app.world()).unwrap();
+        assert_eq!(jump.velocity, 0.0);
+        assert_eq!(transform.translation.y, PLAYER_GROUND);
+    }
+
+    #[test]
+    fn missing_spawn_table_exits_with_an_error() {
+        let mut app = headless_app(SeedMode::Fixed(0));
+        app.insert_resource(DifficultyCurve { spawn_table: "enemies/missing.spawns.ron".to_string(), ..default() });
+
+        assert_eq!(run_until_dead(&mut app, 1000), None);
+        assert_eq!(app.should_exit(), Some(AppExit::error()));
+        assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Loading);
+    }
+
+    #[test]
+    fn run_without_jumps_dies_early() {
+        for seed in 0..8 {
+            let distance = run_with_jumps(seed, 0).expect("a run without jumps has to end");
+
+            assert!(distance < 10_000.0, "seed {} ran {} without jumping", seed, distance);
+        }
+    }
+
+    #[test]
+    fn autopilot_clears_every_generated_course() {
+        for seed in 0..2 {
+            let mut app = headless_app(SeedMode::Fixed(seed));
+            app.add_plugins(AutopilotPlugin);
+
+            assert_eq!(run_until_dead(&mut app, MAX_TICKS), None, "the autopilot died on seed {}", seed);
+            assert!(app.world().resource::<GameData>().current_score > 0.0, "the run never started");
+        }
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_run() {
+        for jump_interval in [0, 45] {
+            assert_eq!(run_with_jumps(7, jump_interval), run_with_jumps(7, jump_interval));
+        }
+    }
+
+    #[test]
+    fn recorded_run_plays_back_to_the_same_distance() {
+        let path = std::env::temp_dir().join(format!("capy_run_replay_{}.ron", std::process::id()));
+
+        let mut app = headless_app(SeedMode::Fixed(3));
+        add_scripted_input(&mut app, 45);
+        app.add_plugins(ReplayPlugin { mode: ReplayMode::Record(path.clone()) });
+        let distance = run_until_dead(&mut app, MAX_TICKS).expect("the recorded run has to end");
+
+        let replay = Replay::load(&path).expect("the replay was saved");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(replay.distance, distance);
+
+        let mut app = headless_app(SeedMode::Fixed(replay.seed));
+        app.add_plugins(ReplayPlugin { mode: ReplayMode::Play(replay) });
+
+        assert_eq!(run_until_dead(&mut app, MAX_TICKS), Some(distance));
+    }
+
+    #[derive(Resource, Default)]
+    struct Heights(Vec<f32>);
+
+    // Keeps the height of the player after every running tick
+    fn add_height_log(app: &mut App) {
+        app.init_resource::<Heights>()
+           .add_systems(FixedUpdate, (|mut heights: ResMut<Heights>, player: Single<&Transform, With<PlayerSprite>>| {
+                heights.0.push(player.translation.y);
+            }).after(GameSystems::Movement).run_if(in_state(GameState::Running)));
+    }
+
+    #[test]
+    fn jump_let_go_of_during_a_pause_plays_back_the_same() {
+        let path = std::env::temp_dir().join(format!("capy_run_paused_replay_{}.ron", std::process::id()));
+
+        let mut app = pointer_app(1.0);
+        app.add_plugins(ReplayPlugin { mode: ReplayMode::Record(path.clone()) });
+        add_height_log(&mut app);
+        start_with_click(&mut app);
+
+        // Paused on the way up, while letting go still makes the jump lower
+        click(&mut app, ButtonState::Pressed);
+        for _ in 0..6 {
+            app.update();
+        }
+        app.world_mut().trigger(GamePause);
+        app.update();
+        click(&mut app, ButtonState::Released);
+        for _ in 0..10 {
+            app.update();
+        }
+        app.world_mut().trigger(GameResume);
+
+        let distance = run_until_dead(&mut app, MAX_TICKS).expect("a run without more jumps has to end");
+        let live = app.world_mut().remove_resource::<Heights>().unwrap().0;
+
+        let replay = Replay::load(&path).expect("the replay was saved");
+        std::fs::remove_file(&path).ok();
+
+        let mut app = headless_app(SeedMode::Fixed(replay.seed));
+        app.add_plugins(ReplayPlugin { mode: ReplayMode::Play(replay) });
+        add_height_log(&mut app);
+
+        assert_eq!(run_until_dead(&mut app, MAX_TICKS), Some(distance));
+        assert_eq!(app.world().resource::<Heights>().0, live);
+    }
+}
@@ -0,0 +1,59 @@
+//! Hitbox module
+//!
+//! Hitboxes are made of axis aligned boxes in world units, relative to the center of the
+//! sprite. The sprites are 120 units wide on screen, so every box stays within +-60.
+//! The enemy hitboxes are part of their definitions.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::POWERUP_SIZE;
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub offset: Vec2,
+    pub half_size: Vec2,
+}
+
+
+impl Aabb {
+    pub const fn new(x: f32, y: f32, half_width: f32, half_height: f32) -> Self {
+        Self {
+            offset: Vec2::new(x, y),
+            half_size: Vec2::new(half_width, half_height),
+        }
+    }
+
+    fn overlaps(&self, position: Vec2, other: &Aabb, other_position: Vec2) -> bool {
+        let distance = ((position + self.offset) - (other_position + other.offset)).abs();
+        let reach = self.half_size + other.half_size;
+
+        distance.x < reach.x && distance.y < reach.y
+    }
+}
+
+
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Hitbox(pub Vec<Aabb>);
+
+
+impl Hitbox {
+    pub fn new(boxes: &[Aabb]) -> Self {
+        Self(boxes.to_vec())
+    }
+
+    pub fn overlaps(&self, position: Vec2, other: &Hitbox, other_position: Vec2) -> bool {
+        self.0.iter().any(|own| {
+            other.0.iter().any(|theirs| own.overlaps(position, theirs, other_position))
+        })
+    }
+}
+
+
+pub const PLAYER_RUNNING_HITBOX: [Aabb; 1] = [Aabb::new(2., -2., 36., 22.)];
+pub const PLAYER_JUMPING_HITBOX: [Aabb; 1] = [Aabb::new(1., 2., 32., 26.)];
+pub const PLAYER_DUCKING_HITBOX: [Aabb; 1] = [Aabb::new(2., -14., 40., 16.)];
+
+// The power-up is a plain square
+pub const POWERUP_HITBOX: [Aabb; 1] = [Aabb::new(0., 0., POWERUP_SIZE / 2., POWERUP_SIZE / 2.)];
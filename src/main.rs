@@ -1,5 +1,6 @@
 //! Main application file
 
+use std::path::{Path, PathBuf};
 use bevy::prelude::*;
 use bevy::window::WindowResolution;
 
@@ -7,7 +8,18 @@ mod constants;
 use constants::*;
 
 mod model;
-use model::Model;
+use model::{DifficultyCurve, GameData, GameState, Model, SeedMode};
+
+mod solvability;
+
+mod hitbox;
+
+mod enemy_definitions;
+
+mod spawn_table;
+
+mod headless;
+use headless::{headless_app, run_until_dead};
 
 mod plugin_scoreboard;
 use plugin_scoreboard::Scoreboard;
@@ -21,19 +33,70 @@ use plugin_enemy::EnemyPlugin;
 mod plugin_ground;
 use plugin_ground::GroundPlugin;
 
+mod plugin_powerup;
+use plugin_powerup::PowerUpPlugin;
+
 mod plugin_signs;
 use plugin_signs::SignPlugin;
 
+mod plugin_spritesheets;
+use plugin_spritesheets::SpritesheetPlugin;
+
+mod plugin_sprite_animation;
+use plugin_sprite_animation::SpriteAnimationPlugin;
+
+mod plugin_actions;
+use plugin_actions::ActionsPlugin;
+
 mod plugin_keyboard_input;
 use plugin_keyboard_input::KeyboardInputPlugin;
 
+mod plugin_gamepad_input;
+use plugin_gamepad_input::GamepadInputPlugin;
+
+mod plugin_pointer_input;
+use plugin_pointer_input::PointerInputPlugin;
+
 mod plugin_game_controller;
 use plugin_game_controller::GameController;
 
+mod plugin_start_screen;
+use plugin_start_screen::StartScreenPlugin;
+
+mod plugin_death_screen;
+use plugin_death_screen::DeathScreenPlugin;
+
+mod plugin_pause_screen;
+use plugin_pause_screen::PauseScreenPlugin;
+
+mod plugin_highscores;
+use plugin_highscores::HighscorePlugin;
+
+mod plugin_hitbox_debug;
+use plugin_hitbox_debug::HitboxDebugPlugin;
+
+mod plugin_replay;
+use plugin_replay::{Replay, ReplayMode, ReplayPlugin};
+
+mod plugin_autopilot;
+use plugin_autopilot::AutopilotPlugin;
+
+
+fn main() -> AppExit {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.iter().any(|arg| arg == "--headless") {
+        return run_headless(&args);
+    }
+
+    // Builds for CI leave out the window and everything which needs system libraries
+    if !cfg!(feature = "window") {
+        eprintln!("Built without the window feature, only --headless runs are available");
+        return AppExit::error();
+    }
 
-fn main() {
-    App::new()
-        .add_plugins(
+    let mut app = App::new();
+    app.add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
                 .set(WindowPlugin {
@@ -45,17 +108,217 @@ fn main() {
                     }),
                     ..default()
                 })
-        )
-        .add_systems(Startup, (setup_camera, setup_background))
-        .add_plugins(Model)
+        );
+
+    // Parsed once logging is set up by the default plugins
+    let replay_mode = replay_mode_from_args(&args);
+    let seed_mode = match &replay_mode {
+        Some(ReplayMode::Play(replay)) => SeedMode::Fixed(replay.seed),
+        _ => seed_mode_from_args(&args),
+    };
+
+    app.add_systems(Startup, (setup_camera, setup_background))
+        .add_plugins(Model { seed_mode })
         .add_plugins(Scoreboard)
+        .add_plugins(SpritesheetPlugin)
+        .add_plugins(SpriteAnimationPlugin)
         .add_plugins(GroundPlugin)
         .add_plugins(SignPlugin)
         .add_plugins(PlayerPlugin)
         .add_plugins(EnemyPlugin)
-        .add_plugins(KeyboardInputPlugin)
+        .add_plugins(PowerUpPlugin)
         .add_plugins(GameController)
-        .run();
+        .add_plugins(HighscorePlugin)
+        .add_plugins(StartScreenPlugin)
+        .add_plugins(DeathScreenPlugin)
+        .add_plugins(PauseScreenPlugin)
+        .add_plugins(HitboxDebugPlugin);
+
+    // A replay plays the recorded inputs and the autopilot its own instead of the keyboard
+    let replaying = matches!(replay_mode, Some(ReplayMode::Play(_)));
+    if !replaying && args.iter().any(|arg| arg == "--autopilot") {
+        app.add_plugins(AutopilotPlugin);
+    } else if !replaying {
+        app.add_plugins((ActionsPlugin, KeyboardInputPlugin, GamepadInputPlugin, PointerInputPlugin));
+    }
+
+    if let Some(mode) = replay_mode {
+        app.add_plugins(ReplayPlugin { mode });
+    }
+
+    app.run()
+}
+
+
+// `--headless --replay <file>` plays a replay without a window and
+// fails if it no longer ends at the recorded distance
+fn run_headless(args: &[String]) -> AppExit {
+    if args.iter().any(|arg| arg == "--autopilot") {
+        return run_autopilot_session(args);
+    }
+
+    let Some(path) = arg_value(args, "--replay") else {
+        eprintln!("--headless needs inputs to play, pass --replay <file> or --autopilot");
+        return AppExit::error();
+    };
+
+    let replay = match Replay::load(Path::new(path)) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Could not load replay {}: {}", path, err);
+            return AppExit::error();
+        }
+    };
+
+    let expected = replay.distance;
+    let mut app = headless_app(SeedMode::Fixed(replay.seed));
+    app.add_plugins(ReplayPlugin { mode: ReplayMode::Play(replay) });
+
+    match run_until_dead(&mut app, HEADLESS_MAX_TICKS) {
+        Some(distance) if distance == expected => {
+            println!("Replay ended at {}", distance);
+            AppExit::Success
+        }
+        Some(distance) => {
+            eprintln!("Replay ended at {} instead of {}", distance, expected);
+            AppExit::error()
+        }
+        None => {
+            eprintln!("Replay did not end within {} ticks", HEADLESS_MAX_TICKS);
+            AppExit::error()
+        }
+    }
+}
+
+
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    let position = args.iter().position(|arg| arg == name)?;
+    args.get(position + 1)
+}
+
+
+// `--seed <number>` replays a course, `--daily` plays the course of the day
+fn seed_mode_from_args(args: &[String]) -> SeedMode {
+    if args.iter().any(|arg| arg == "--daily") {
+        return SeedMode::Daily;
+    }
+
+    if !args.iter().any(|arg| arg == "--seed") {
+        return SeedMode::Random;
+    }
+
+    match arg_value(args, "--seed").map(|seed| seed.parse::<u64>()) {
+        Some(Ok(seed)) => SeedMode::Fixed(seed),
+        _ => {
+            warn!("--seed expects a number, using a random seed");
+            SeedMode::Random
+        }
+    }
+}
+
+
+// `--record <file>` saves the inputs of every run, `--replay <file>` plays them back
+fn replay_mode_from_args(args: &[String]) -> Option<ReplayMode> {
+    if let Some(path) = arg_value(args, "--replay") {
+        return match Replay::load(Path::new(path)) {
+            Ok(replay) => Some(ReplayMode::Play(replay)),
+            Err(err) => {
+                warn!("Could not load replay {}: {}", path, err);
+                None
+            }
+        };
+    }
+
+    arg_value(args, "--record").map(|path| ReplayMode::Record(PathBuf::from(path)))
+}
+
+
+// `--headless --autopilot` lets the autopilot play `--runs <count>` runs on the seeds
+// from `--seed <number>` on, the difficulty can be changed for balancing with
+// `--initial-velocity`, `--acceleration`, `--max-velocity` and `--spawn-table <asset path>`
+fn run_autopilot_session(args: &[String]) -> AppExit {
+    let Some(runs) = arg_number(args, "--runs", 1u64) else {
+        return AppExit::error();
+    };
+    let Some(first_seed) = arg_number(args, "--seed", 0u64) else {
+        return AppExit::error();
+    };
+    let Some(curve) = difficulty_from_args(args) else {
+        return AppExit::error();
+    };
+
+    let mut distances = Vec::new();
+
+    for seed in first_seed..first_seed + runs {
+        let mut app = headless_app(SeedMode::Fixed(seed));
+        app.insert_resource(curve.clone())
+           .add_plugins(AutopilotPlugin);
+
+        match run_until_dead(&mut app, HEADLESS_MAX_TICKS) {
+            Some(distance) => {
+                println!("Seed {}: died at {}", seed, distance as usize);
+                distances.push(distance);
+            }
+            // The enemies or the spawn table could not be loaded, no run can be played
+            None if *app.world().resource::<State<GameState>>().get() == GameState::Loading => {
+                eprintln!("Seed {}: the enemies or the spawn table {} could not be loaded", seed, curve.spawn_table);
+                return AppExit::error();
+            }
+            None => {
+                let distance = app.world().resource::<GameData>().current_score;
+                println!("Seed {}: still running at {} after {} ticks", seed, distance as usize, HEADLESS_MAX_TICKS);
+                distances.push(distance);
+            }
+        }
+    }
+
+    let average = distances.iter().sum::<f32>() / distances.len().max(1) as f32;
+    println!("Average distance over {} runs: {}", distances.len(), average as usize);
+
+    AppExit::Success
+}
+
+
+// The value of an optional argument, None after reporting a value which can't be parsed
+fn arg_number<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> Option<T> {
+    if !args.iter().any(|arg| arg == name) {
+        return Some(default);
+    }
+
+    match arg_value(args, name).map(|value| value.parse::<T>()) {
+        Some(Ok(value)) => Some(value),
+        _ => {
+            eprintln!("{} expects a number", name);
+            None
+        }
+    }
+}
+
+
+// Enemy gaps are scaled by the velocity, the world can't be generated once it stops
+fn difficulty_from_args(args: &[String]) -> Option<DifficultyCurve> {
+    let curve = DifficultyCurve::default();
+
+    let curve = DifficultyCurve {
+        initial_velocity: arg_number(args, "--initial-velocity", curve.initial_velocity)?,
+        acceleration: arg_number(args, "--acceleration", curve.acceleration)?,
+        max_velocity: arg_number(args, "--max-velocity", curve.max_velocity)?,
+        spawn_table: arg_value(args, "--spawn-table").cloned().unwrap_or(curve.spawn_table),
+    };
+
+    for (name, velocity) in [("--initial-velocity", curve.initial_velocity), ("--max-velocity", curve.max_velocity)] {
+        if !velocity.is_finite() || velocity <= 0.0 {
+            eprintln!("{} has to be above zero", name);
+            return None;
+        }
+    }
+
+    if !curve.acceleration.is_finite() || curve.acceleration < 0.0 {
+        eprintln!("--acceleration can't be negative");
+        return None;
+    }
+
+    Some(curve)
 }
 
 
@@ -1,28 +1,180 @@
 use bevy::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
 
-use crate::constants::INITIAL_VELOCITY;
+use crate::constants::{ENEMY_MAXIMUM_SPACE, ENEMY_MINIMUM_SPACE, ENEMY_SPAWN_TABLE_FILE, INITIAL_VELOCITY, MAXIMUM_VELOCITY, VELOCITY_ACCELERATION};
 
 
-#[derive(PartialEq)]
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum GameState {
+    // Until the enemy definitions are loaded
+    #[default]
+    Loading,
     WaitingForStart,
     Running,
+    Paused,
     Dead,
     Reset
 }
 
 
+// Fixed update order of a game tick, so a run plays out the same every time
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameSystems {
+    Clock,
+    Input,
+    Progress,
+    Movement,
+    Collision,
+}
+
+
+// Fixed update ticks since the current state was entered
+#[derive(Resource, Default)]
+pub struct StateTicks(pub u64);
+
+
 #[derive(Resource)]
 pub struct GameData {
-    pub game_state: GameState,
     pub previous_score: f32,
     pub current_score: f32,
     pub velocity: f32,
+    pub enemies_jumped: u32,
+    pub killed_by: Option<KilledBy>,
+    pub tombstone_distance: Option<f32>,
+}
+
+
+#[derive(Resource, Clone)]
+pub struct DifficultyCurve {
+    pub initial_velocity: f32,
+    pub acceleration: f32,
+    pub max_velocity: f32,
+    // Asset with the spawn weights of the enemies
+    pub spawn_table: String,
+}
+
+
+impl Default for DifficultyCurve {
+    fn default() -> Self {
+        Self {
+            initial_velocity: INITIAL_VELOCITY,
+            acceleration: VELOCITY_ACCELERATION,
+            max_velocity: MAXIMUM_VELOCITY,
+            spawn_table: ENEMY_SPAWN_TABLE_FILE.to_string(),
+        }
+    }
+}
+
+
+impl DifficultyCurve {
+    pub fn velocity(&self, distance: f32) -> f32 {
+        (self.initial_velocity + distance * self.acceleration).min(self.max_velocity)
+    }
+
+    // Scale the enemy gaps with the velocity so they take the same time to cross,
+    // otherwise the player can't land and jump again at high speed
+    pub fn enemy_spacing(&self, velocity: f32) -> (f32, f32) {
+        let scale = self.spacing_scale(velocity);
+        (ENEMY_MINIMUM_SPACE * scale, ENEMY_MAXIMUM_SPACE * scale)
+    }
+
+    // Distances between enemies are given at the initial velocity
+    pub fn spacing_scale(&self, velocity: f32) -> f32 {
+        velocity / self.initial_velocity
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeedMode {
+    Random,
+    // Given on the command line, every run replays it
+    Fixed(u64),
+    // Derived from the date, everyone plays the same course on the same day
+    Daily,
+}
+
+
+// All world generation draws from here, every generator has its own stream so the
+// order in which the systems run does not change the world
+#[derive(Resource)]
+pub struct WorldRng {
+    pub seed: u64,
+    pub mode: SeedMode,
+    // Date of the daily course, kept for the whole run even if it passes midnight
+    pub daily_date: Option<String>,
+    pub enemies: StdRng,
+    pub ground: StdRng,
+    pub powerups: StdRng,
+}
+
+
+impl WorldRng {
+    pub fn new(mode: SeedMode) -> Self {
+        let daily_date = match mode {
+            SeedMode::Daily => Some(today()),
+            _ => None,
+        };
+
+        let seed = match (mode, &daily_date) {
+            (SeedMode::Fixed(seed), _) => seed,
+            (SeedMode::Daily, Some(date)) => daily_seed(date),
+            _ => rand::random(),
+        };
+
+        Self {
+            seed,
+            mode,
+            daily_date,
+            enemies: StdRng::seed_from_u64(seed),
+            ground: StdRng::seed_from_u64(seed.wrapping_add(1)),
+            powerups: StdRng::seed_from_u64(seed.wrapping_add(2)),
+        }
+    }
+
+    fn reseed(&mut self) {
+        *self = Self::new(self.mode);
+    }
+}
+
+
+pub fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+
+// FNV-1a, unlike the std hasher it gives the same seed on every platform and version
+fn daily_seed(date: &str) -> u64 {
+    date.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum KilledBy {
+    Water,
+    // Name of the enemy's definition
+    Enemy(String),
+}
+
+
+impl KilledBy {
+    pub fn name(&self) -> &str {
+        match self {
+            KilledBy::Water => "Water",
+            KilledBy::Enemy(name) => name,
+        }
+    }
 }
 
 
 #[derive(Event)]
-pub struct GameEnd;
+pub struct GameEnd {
+    pub killed_by: KilledBy,
+}
 
 
 #[derive(Event)]
@@ -33,29 +185,110 @@ pub struct GameStart;
 pub struct GameReset;
 
 
+#[derive(Event)]
+pub struct GamePause;
+
+
+#[derive(Event)]
+pub struct GameResume;
+
+
 #[derive(Event)]
 pub struct PlayerJump;
 
 
-pub struct Model;
+// Letting go of the jump button early makes the jump lower
+#[derive(Event)]
+pub struct PlayerJumpReleased;
+
+
+// The player ducks on the ground as long as the button is held
+#[derive(Event)]
+pub struct PlayerDuck;
+
+
+#[derive(Event)]
+pub struct PlayerStandUp;
+
+
+pub struct Model {
+    pub seed_mode: SeedMode,
+}
+
 impl Plugin for Model {
     fn build(&self, app: &mut App) {
-        app.insert_resource(GameData {
-            game_state: GameState::WaitingForStart,
-            previous_score: 0.,
-            current_score: 0.,
-            velocity: INITIAL_VELOCITY
-        }).add_observer(handle_model_reset);
+        app.init_state::<GameState>()
+           .init_resource::<DifficultyCurve>()
+           .insert_resource(WorldRng::new(self.seed_mode))
+           .insert_resource(GameData {
+                previous_score: 0.,
+                current_score: 0.,
+                velocity: INITIAL_VELOCITY,
+                enemies_jumped: 0,
+                killed_by: None,
+                tombstone_distance: None,
+            })
+           .init_resource::<StateTicks>()
+           .configure_sets(FixedUpdate, (
+                GameSystems::Clock,
+                GameSystems::Input,
+                GameSystems::Progress,
+                GameSystems::Movement,
+                GameSystems::Collision,
+            ).chain())
+           .add_systems(FixedUpdate, count_state_ticks.in_set(GameSystems::Clock).run_if(not(in_state(GameState::Paused))))
+           .add_systems(OnEnter(GameState::WaitingForStart), reset_state_ticks)
+           // Resuming from a pause continues the count, replays rely on it
+           .add_systems(OnTransition { exited: GameState::WaitingForStart, entered: GameState::Running }, reset_state_ticks)
+           .add_systems(OnExit(GameState::Dead), reseed_world)
+           .add_systems(OnEnter(GameState::Reset), reset_model);
     }
 }
 
-fn handle_model_reset(
-    _evt: On<GameReset>,
+fn reset_model(
+    curve: Res<DifficultyCurve>,
     mut game: ResMut<GameData>
 )
 {
     game.previous_score = game.current_score;
+    game.tombstone_distance = Some(game.current_score);
     game.current_score = 0.;
-    game.velocity = INITIAL_VELOCITY;
-    game.game_state = GameState::WaitingForStart;
+    game.velocity = curve.initial_velocity;
+    game.enemies_jumped = 0;
+    game.killed_by = None;
+}
+
+
+// Runs before OnEnter(Reset), where the world is generated again
+fn reseed_world(
+    mut rng: ResMut<WorldRng>
+)
+{
+    rng.reseed();
+}
+
+
+// The world stops in the tick the player dies, not only once the state changed
+pub fn world_running(
+    state: Res<State<GameState>>,
+    game: Res<GameData>
+) -> bool
+{
+    *state.get() == GameState::Running && game.killed_by.is_none()
+}
+
+
+fn count_state_ticks(
+    mut ticks: ResMut<StateTicks>
+)
+{
+    ticks.0 += 1;
+}
+
+
+fn reset_state_ticks(
+    mut ticks: ResMut<StateTicks>
+)
+{
+    ticks.0 = 0;
 }
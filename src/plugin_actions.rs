@@ -0,0 +1,324 @@
+//! Actions plugin module
+//!
+//! Input plugins turn their buttons into actions through the bindings, the game only reacts
+//! to actions. The bindings are read from a config file, which is written with the defaults
+//! on the first start so they can be changed there.
+//!
+//! Devices only report presses and releases for the frame they happened in, and a frame can
+//! run any number of fixed update ticks. Their actions are queued every frame and played in
+//! the next tick, so each one reaches the game exactly once.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use bevy::input::InputSystems;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{BINDINGS_FILE, CONFIG_DIRECTORY};
+use crate::model::{GamePause, GameReset, GameResume, GameStart, GameState, GameSystems, PlayerDuck, PlayerJump, PlayerJumpReleased, PlayerStandUp};
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        // Tests bring their own bindings instead of the config file
+        if !app.world().contains_resource::<Bindings>() {
+            app.insert_resource(Bindings::load());
+        }
+
+        app.init_resource::<Actions>()
+           .configure_sets(PreUpdate, ActionSystems.after(InputSystems))
+           .add_systems(FixedUpdate, play_actions.in_set(GameSystems::Input))
+           .add_observer(handle_action)
+           .add_observer(handle_action_release);
+    }
+}
+
+
+// Input plugins queue their actions in here, after the input of the frame was read
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionSystems;
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Jump,
+    Start,
+    Restart,
+    Pause,
+    Duck,
+}
+
+
+// Touches have no buttons, they are told apart by where and with how many fingers they start
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchGesture {
+    // Anywhere on the screen
+    Tap,
+    UpperHalf,
+    LowerHalf,
+    // A second finger touching while the first is held
+    TwoFingers,
+}
+
+
+impl TouchGesture {
+    fn describe(&self) -> &'static str {
+        match self {
+            TouchGesture::Tap => "tap",
+            TouchGesture::UpperHalf => "tap on the upper half",
+            TouchGesture::LowerHalf => "touch on the lower half",
+            TouchGesture::TwoFingers => "two-finger tap",
+        }
+    }
+}
+
+
+#[derive(Event)]
+pub struct ActionPressed(pub Action);
+
+
+#[derive(Event)]
+pub struct ActionReleased(pub Action);
+
+
+// Actions of the devices which were not played in a tick yet, in the order they happened
+#[derive(Resource, Default)]
+pub struct Actions {
+    queued: Vec<(Action, bool)>,
+    // Let go of during a pause, the game only sees it once it runs again
+    released_while_paused: Vec<Action>,
+}
+
+
+impl Actions {
+    pub fn press(&mut self, action: Action) {
+        self.queued.push((action, true));
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.queued.push((action, false));
+    }
+}
+
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Bindings {
+    pub keyboard: BTreeMap<Action, Vec<KeyCode>>,
+    pub gamepad: BTreeMap<Action, Vec<GamepadButton>>,
+    pub mouse: BTreeMap<Action, Vec<MouseButton>>,
+    pub touch: BTreeMap<Action, Vec<TouchGesture>>,
+}
+
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            keyboard: BTreeMap::from([
+                (Action::Jump, vec![KeyCode::Space, KeyCode::ArrowUp]),
+                (Action::Start, vec![KeyCode::Space, KeyCode::Enter]),
+                (Action::Restart, vec![KeyCode::Space, KeyCode::Enter]),
+                (Action::Pause, vec![KeyCode::Escape, KeyCode::KeyP]),
+                (Action::Duck, vec![KeyCode::ArrowDown]),
+            ]),
+            gamepad: BTreeMap::from([
+                (Action::Jump, vec![GamepadButton::South]),
+                (Action::Start, vec![GamepadButton::South]),
+                (Action::Restart, vec![GamepadButton::South]),
+                (Action::Pause, vec![GamepadButton::Start]),
+                (Action::Duck, vec![GamepadButton::East, GamepadButton::DPadDown]),
+            ]),
+            mouse: BTreeMap::from([
+                (Action::Jump, vec![MouseButton::Left]),
+                (Action::Start, vec![MouseButton::Left]),
+                (Action::Restart, vec![MouseButton::Left]),
+                (Action::Duck, vec![MouseButton::Right]),
+            ]),
+            touch: BTreeMap::from([
+                (Action::Jump, vec![TouchGesture::UpperHalf]),
+                (Action::Start, vec![TouchGesture::Tap]),
+                (Action::Restart, vec![TouchGesture::Tap]),
+                (Action::Pause, vec![TouchGesture::TwoFingers]),
+                (Action::Duck, vec![TouchGesture::LowerHalf]),
+            ]),
+        }
+    }
+}
+
+
+fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIRECTORY).join(BINDINGS_FILE))
+}
+
+
+impl Bindings {
+    fn load() -> Self {
+        let Some(path) = path() else {
+            return Self::default();
+        };
+
+        // Without a file the defaults are written, so there is something to edit
+        let Ok(content) = fs::read_to_string(&path) else {
+            let bindings = Self::default();
+            bindings.save(&path);
+            return bindings;
+        };
+
+        ron::from_str(&content).unwrap_or_else(|err| {
+            warn!("Ignoring corrupt bindings file {}: {}", path.display(), err);
+            Self::default()
+        })
+    }
+
+    // Names the buttons bound to an action for the screens, like "Space, Enter or tap"
+    pub fn describe(&self, action: Action) -> Option<String> {
+        let mut buttons: Vec<String> = Vec::new();
+        buttons.extend(self.keyboard.get(&action).into_iter().flatten().map(|key| format!("{:?}", key)));
+        buttons.extend(self.gamepad.get(&action).into_iter().flatten().map(|button| format!("gamepad {:?}", button)));
+        buttons.extend(self.mouse.get(&action).into_iter().flatten().map(|button| format!("{:?} click", button).to_lowercase()));
+        buttons.extend(self.touch.get(&action).into_iter().flatten().map(|gesture| gesture.describe().to_string()));
+
+        let last = buttons.pop()?;
+        if buttons.is_empty() {
+            return Some(last);
+        }
+        Some(format!("{} or {}", buttons.join(", "), last))
+    }
+
+    fn save(&self, path: &Path) {
+        if let Some(directory) = path.parent()
+            && let Err(err) = fs::create_dir_all(directory)
+        {
+            warn!("Could not create {}: {}", directory.display(), err);
+            return;
+        }
+
+        match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(content) => {
+                if let Err(err) = fs::write(path, content) {
+                    warn!("Could not write bindings to {}: {}", path.display(), err);
+                }
+            }
+            Err(err) => warn!("Could not serialize bindings: {}", err),
+        }
+    }
+}
+
+
+// Queues the actions of a device whose bound buttons were pressed or released
+pub fn queue_actions<T>(
+    actions: &mut Actions,
+    bindings: &BTreeMap<Action, Vec<T>>,
+    just_pressed: impl Fn(&T) -> bool,
+    just_released: impl Fn(&T) -> bool,
+)
+{
+    for (action, buttons) in bindings {
+        if buttons.iter().any(&just_pressed) {
+            actions.press(*action);
+        }
+        if buttons.iter().any(&just_released) {
+            actions.release(*action);
+        }
+    }
+}
+
+
+// Frames without a tick keep their actions for the next one. Buttons let go of while paused
+// are released in the first tick after the pause, a replay can only play them in a tick
+// which moves the player.
+fn play_actions(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    mut actions: ResMut<Actions>,
+)
+{
+    let paused = *state.get() == GameState::Paused;
+
+    if !paused {
+        for action in std::mem::take(&mut actions.released_while_paused) {
+            commands.trigger(ActionReleased(action));
+        }
+    }
+
+    for (action, pressed) in std::mem::take(&mut actions.queued) {
+        if pressed {
+            // Pressed again before resuming, the button is still held
+            actions.released_while_paused.retain(|&released| released != action);
+            commands.trigger(ActionPressed(action));
+        } else if paused {
+            actions.released_while_paused.push(action);
+        } else {
+            commands.trigger(ActionReleased(action));
+        }
+    }
+}
+
+
+// Each action only does something in the state it belongs to, so one button can start,
+// jump and restart
+fn handle_action(
+    evt: On<ActionPressed>,
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+)
+{
+    match (evt.0, state.get()) {
+        (Action::Start, GameState::WaitingForStart) => commands.trigger(GameStart),
+        (Action::Jump, GameState::Running) => commands.trigger(PlayerJump),
+        (Action::Duck, GameState::Running) => commands.trigger(PlayerDuck),
+        (Action::Restart, GameState::Dead) => commands.trigger(GameReset),
+        (Action::Pause, GameState::Running) => commands.trigger(GamePause),
+        (Action::Pause, GameState::Paused) => commands.trigger(GameResume),
+        _ => {}
+    }
+}
+
+
+fn handle_action_release(
+    evt: On<ActionReleased>,
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+)
+{
+    match (evt.0, state.get()) {
+        (Action::Jump, GameState::Running) => commands.trigger(PlayerJumpReleased),
+        (Action::Duck, GameState::Running) => commands.trigger(PlayerStandUp),
+        _ => {}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_survive_the_config_file() {
+        let content = ron::ser::to_string_pretty(&Bindings::default(), ron::ser::PrettyConfig::default()).unwrap();
+
+        assert_eq!(ron::from_str::<Bindings>(&content).unwrap(), Bindings::default());
+    }
+
+    #[test]
+    fn missing_devices_keep_their_defaults() {
+        let bindings: Bindings = ron::from_str("(keyboard: { Jump: [KeyW] })").unwrap();
+
+        assert_eq!(bindings.keyboard, BTreeMap::from([(Action::Jump, vec![KeyCode::KeyW])]));
+        assert_eq!(bindings.gamepad, Bindings::default().gamepad);
+    }
+
+    #[test]
+    fn actions_are_described_by_their_buttons() {
+        let bindings: Bindings = ron::from_str("(keyboard: { Start: [Enter] }, gamepad: {}, mouse: {}, touch: {})").unwrap();
+
+        assert_eq!(bindings.describe(Action::Start).as_deref(), Some("Enter"));
+        assert_eq!(bindings.describe(Action::Pause), None);
+        assert_eq!(
+            Bindings::default().describe(Action::Restart).as_deref(),
+            Some("Space, Enter, gamepad South, left click or tap"),
+        );
+    }
+}
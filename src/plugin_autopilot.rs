@@ -0,0 +1,118 @@
+//! Autopilot plugin module
+//!
+//! Plays the game without input. Every tick on the ground the autopilot checks whether it can
+//! stay on the ground and still clear the enemies and water in front of it, and jumps once
+//! it can't.
+//! On the ground it ducks whenever running on would hit an enemy.
+
+use std::time::Duration;
+use bevy::prelude::*;
+
+use crate::constants::*;
+use crate::hitbox::Hitbox;
+use crate::model::{GameData, GameReset, GameStart, GameState, GameSystems, PlayerDuck, PlayerJump, PlayerStandUp};
+use crate::plugin_enemy::EnemySprite;
+use crate::plugin_ground::{GroundTile, GroundType};
+use crate::plugin_highscores::RecordHighscores;
+use crate::plugin_player::{Jump, PlayerSprite};
+use crate::solvability::{JumpArc, must_duck, must_jump};
+
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AutopilotRestart(Timer::default()))
+           .insert_resource(RecordHighscores(false))
+           .add_systems(FixedUpdate, drive.in_set(GameSystems::Input))
+           .add_systems(OnEnter(GameState::Dead), wait_for_restart)
+           .add_systems(Update, restart.run_if(in_state(GameState::Dead)));
+    }
+}
+
+
+#[derive(Resource)]
+struct AutopilotRestart(Timer);
+
+
+fn drive(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    game: Res<GameData>,
+    player_query: Query<(&Transform, &Jump), With<PlayerSprite>>,
+    enemy_query: Query<(&Transform, &Hitbox), With<EnemySprite>>,
+    ground_query: Query<(&Transform, &GroundType), With<GroundTile>>,
+    mut ducking: Local<bool>,
+) {
+    match state.get() {
+        GameState::WaitingForStart => {
+            // The player stands up again for every run
+            *ducking = false;
+            commands.trigger(GameStart);
+        }
+        GameState::Running => {
+            let Ok((player_transform, jump)) = player_query.single() else {
+                return;
+            };
+
+            // A jump can't be steered, so there is only something to decide on the ground
+            if jump.velocity != 0.0 || game.killed_by.is_some() {
+                return;
+            }
+
+            let arc = JumpArc { initial_velocity: JUMP_VELOCITY, gravity: jump.gravity };
+            let lookahead = arc.jump_length(game.velocity) * AUTOPILOT_LOOKAHEAD_JUMPS + SCALED_TILE_SIZE;
+            let player_position = player_transform.translation.truncate();
+
+            let ahead: Vec<(Vec2, Hitbox)> = enemy_query.iter()
+                .map(|(transform, hitbox)| (transform.translation.truncate() - player_position, hitbox.clone()))
+                .filter(|(position, _)| position.x >= -SCALED_TILE_SIZE && position.x <= lookahead)
+                .collect();
+
+            let water: Vec<(f32, f32)> = ground_query.iter()
+                .filter(|(_, ground_type)| **ground_type == GroundType::Water)
+                .map(|(transform, _)| transform.translation.x - player_position.x)
+                .filter(|&x| x >= -SCALED_TILE_SIZE && x <= lookahead)
+                .map(|x| (x - SCALED_TILE_SIZE / 2., x + SCALED_TILE_SIZE / 2.))
+                .collect();
+
+            if must_jump(&ahead, &water, game.velocity, &arc) {
+                commands.trigger(PlayerJump);
+                return;
+            }
+
+            let duck = must_duck(&ahead, game.velocity);
+            if duck != *ducking {
+                *ducking = duck;
+                if duck {
+                    commands.trigger(PlayerDuck);
+                } else {
+                    commands.trigger(PlayerStandUp);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+
+fn wait_for_restart(
+    mut restart: ResMut<AutopilotRestart>,
+)
+{
+    restart.0 = Timer::new(Duration::from_secs_f32(AUTOPILOT_RESTART_SECS), TimerMode::Once);
+}
+
+
+// Demo runs go on forever
+fn restart(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut restart: ResMut<AutopilotRestart>,
+)
+{
+    restart.0.tick(time.delta());
+
+    if restart.0.just_finished() {
+        commands.trigger(GameReset);
+    }
+}
@@ -0,0 +1,146 @@
+//! Death screen plugin module
+
+use bevy::prelude::*;
+
+use crate::constants::*;
+use crate::model::{GameData, GameState, WorldRng};
+use crate::plugin_actions::{Action, Bindings};
+use crate::plugin_game_controller::RestartLock;
+use crate::plugin_highscores::{Highscores, record_highscore, spawn_highscore_table};
+use crate::plugin_player::{DEAD_CLIP, PlayerSprite};
+use crate::plugin_sprite_animation::ClipFinished;
+
+#[derive(Component)]
+struct DeathScreenUi;
+
+#[derive(Component)]
+struct RestartPrompt;
+
+
+pub struct DeathScreenPlugin;
+
+impl Plugin for DeathScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Dead), setup_death_screen.after(record_highscore))
+           .add_systems(Update, show_restart_prompt.run_if(in_state(GameState::Dead)))
+           .add_systems(OnExit(GameState::Dead), despawn_death_screen)
+           .add_observer(show_death_screen);
+    }
+}
+
+
+fn screen_text(text: String) -> (Text, TextFont, TextColor) {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: SCREEN_TEXT_FONT_SIZE,
+            ..default()
+        },
+        TextColor(SCREEN_TEXT_COLOR),
+    )
+}
+
+
+fn setup_death_screen(
+    mut commands: Commands,
+    game: Res<GameData>,
+    rng: Res<WorldRng>,
+    highscores: Res<Highscores>,
+    bindings: Option<Res<Bindings>>,
+)
+{
+    let killed_by = game.killed_by.as_ref().map_or("Unknown", |killed_by| killed_by.name());
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: SCREEN_ROW_GAP,
+            ..default()
+        },
+        BackgroundColor(SCREEN_OVERLAY_COLOR),
+        // Hidden until the player is seen dying
+        Visibility::Hidden,
+        DeathScreenUi,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("Game Over"),
+            TextFont {
+                font_size: SCREEN_TITLE_FONT_SIZE,
+                ..default()
+            },
+            TextColor(SCREEN_TITLE_COLOR),
+        ));
+
+        parent.spawn(screen_text(format!("Distance: {}", game.current_score as usize)));
+        parent.spawn(screen_text(format!("Previous score: {}", game.previous_score as usize)));
+        parent.spawn(screen_text(format!("Enemies jumped: {}", game.enemies_jumped)));
+        parent.spawn(screen_text(format!("Killed by: {}", killed_by)));
+        parent.spawn(screen_text(format!("Seed: {}", rng.seed)));
+
+        if highscores.last_rank.is_some() {
+            parent.spawn(screen_text("New high score!".to_string()));
+        }
+
+        if let Some(date) = &rng.daily_date {
+            let best = highscores.daily_best(date).map_or(0, |distance| distance as usize);
+            parent.spawn(screen_text(format!("Daily best {}: {}", date, best)));
+
+            if highscores.last_was_daily_best {
+                parent.spawn(screen_text("New daily best!".to_string()));
+            }
+        }
+
+        spawn_highscore_table(parent, &highscores);
+
+        // Hidden until the restart lock is released, the autopilot and replays restart on their own
+        if let Some(buttons) = bindings.and_then(|bindings| bindings.describe(Action::Restart)) {
+            parent.spawn((
+                screen_text(format!("Press {} to restart", buttons)),
+                Visibility::Hidden,
+                RestartPrompt,
+            ));
+        }
+    });
+}
+
+
+fn show_death_screen(
+    evt: On<ClipFinished>,
+    players: Query<(), With<PlayerSprite>>,
+    mut query: Query<&mut Visibility, With<DeathScreenUi>>,
+)
+{
+    if evt.clip == DEAD_CLIP && players.contains(evt.entity) {
+        for mut visibility in &mut query {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+
+fn show_restart_prompt(
+    lock: Res<RestartLock>,
+    mut query: Query<&mut Visibility, With<RestartPrompt>>,
+)
+{
+    if lock.0.is_finished() {
+        for mut visibility in &mut query {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+
+fn despawn_death_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<DeathScreenUi>>,
+)
+{
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
@@ -1,67 +1,71 @@
 //! Enemy plugin module
+//!
+//! The enemies are read from their definitions asset into a roster together with the spawn
+//! table of the difficulty, the game waits in the loading state until it is there. Without
+//! enemies there is no run to play, so the game exits with an error if either can't be loaded.
 
-use std::time::Duration;
+use bevy::asset::AssetLoadFailedEvent;
 use bevy::prelude::*;
 use rand::Rng;
+use rand::rngs::StdRng;
 
 use crate::constants::*;
-use crate::model::{GameData, GameReset, GameState};
+use crate::enemy_definitions::{EnemyDefinition, EnemyDefinitions, EnemyDefinitionsLoader};
+use crate::model::{DifficultyCurve, GameData, GameState, GameSystems, WorldRng, world_running};
+use crate::plugin_sprite_animation::{SpriteAnimation, SpriteClip, SpriteClips};
+use crate::plugin_spritesheets::SpritesheetLoader;
+use crate::solvability::{JumpArc, is_clearable};
+use crate::spawn_table::{SpawnPlan, SpawnTable, SpawnTableLoader};
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_enemies)
-            .add_systems(Update, execute_animations)
-            .add_systems(FixedUpdate, move_enemy)
-            .add_observer(handle_enemy_reset);
+        app.init_asset::<EnemyDefinitions>()
+            .init_asset_loader::<EnemyDefinitionsLoader>()
+            .init_asset::<SpawnTable>()
+            .init_asset_loader::<SpawnTableLoader>()
+            .add_systems(Startup, load_enemy_definitions)
+            .add_systems(Update, build_roster.run_if(not(resource_exists::<EnemyRoster>)))
+            .add_systems(FixedUpdate, move_enemy.in_set(GameSystems::Movement).in_set(EnemyMovement).run_if(world_running))
+            .add_systems(OnExit(GameState::Loading), spawn_first_pattern)
+            .add_systems(OnEnter(GameState::Reset), (despawn_enemies, spawn_first_pattern).chain());
     }
 }
 
+// Other movement which depends on where the enemies are runs after this
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnemyMovement;
+
+// Index of the enemy's definition in the roster
 #[derive(Component, Debug, Clone, Copy, PartialEq)]
-enum EnemyType {
-    Eagle,
-    Lion,
-    Croco,
-}
+pub struct EnemyKind(pub usize);
 
-#[derive(Component, Clone)]
-struct AnimationConfig {
-    first_sprite_index: usize,
-    last_sprite_index: usize,
-    fps: u8,
-    frame_timer: Timer,
-}
+// Enemies have a single clip running all the time
+const ENEMY_CLIP: &str = "moving";
 
 
-#[derive(Clone)]
-struct EnemyTexture {
-    image: Handle<Image>,
-    layout: Handle<TextureAtlasLayout>,
-    animation: AnimationConfig,
-}
+#[derive(Resource)]
+struct EnemyDefinitionsHandle(Handle<EnemyDefinitions>);
 
 
-#[derive(Resource, Clone)]
-struct EnemyTextures {
-    eagle: EnemyTexture,
-    lion: EnemyTexture,
-    croco: EnemyTexture
-}
+#[derive(Resource)]
+struct SpawnTableHandle(Handle<SpawnTable>);
 
 
-impl AnimationConfig {
-    fn new(first: usize, last: usize, fps: u8) -> Self {
-        Self {
-            first_sprite_index: first,
-            last_sprite_index: last,
-            fps,
-            frame_timer: Self::timer_from_fps(fps),
-        }
-    }
+// The loaded definitions with their textures
+#[derive(Resource)]
+pub struct EnemyRoster {
+    definitions: Vec<EnemyDefinition>,
+    // Spawn weights of the definitions and the patterns by distance
+    spawn_plan: SpawnPlan,
+    clips: Vec<SpriteClips>,
+}
 
-    fn timer_from_fps(fps: u8) -> Timer {
-        Timer::new(Duration::from_secs_f32(1.0 / (fps as f32)), TimerMode::Once)
+
+impl EnemyRoster {
+    pub fn definition(&self, kind: EnemyKind) -> &EnemyDefinition {
+        &self.definitions[kind.0]
     }
 }
 
@@ -69,157 +73,299 @@ impl AnimationConfig {
 #[derive(Component)]
 pub struct EnemySprite;
 
-fn setup_enemies(
+fn enemy_clips(loader: &mut Option<SpritesheetLoader>, definition: &EnemyDefinition) -> SpriteClips {
+    let sheet = loader.as_mut()
+        .map(|loader| loader.load(&definition.spritesheet, definition.frame_size))
+        .unwrap_or_default();
+
+    SpriteClips::default().with(ENEMY_CLIP, SpriteClip::new(sheet, definition.fps as f32, definition.playback))
+}
+
+
+// The spawn table belongs to the difficulty
+fn load_enemy_definitions(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    curve: Res<DifficultyCurve>,
 ) {
-    // Load enemy spritesheets
-    let eagle_texture = asset_server.load("textures/enemies/eagle/flying.png");
-    let lion_texture = asset_server.load("textures/enemies/lion/running.png");
-    let croco_texture = asset_server.load("textures/enemies/crocodile/running.png");
-
-    // Create layouts for each enemy type
-    // Eagle: 1 sprite (1 row, 1 column) - assume 240x240 like player
-    let eagle_layout = TextureAtlasLayout::from_grid(UVec2::splat(240), 1, 1, None, None);
-    let eagle_layout_handle = texture_atlas_layouts.add(eagle_layout);
-
-    // Lion: 1 rows, 1 column - each row is a different enemy (240x240 each)
-    let lion_layout = TextureAtlasLayout::from_grid(UVec2::splat(240), 1, 1, None, None);
-    let lion_layout_handle = texture_atlas_layouts.add(lion_layout);
-
-        // Croco: 1 rows, 1 column - each row is a different enemy (240x240 each)
-    let croco_layout = TextureAtlasLayout::from_grid(UVec2::splat(240), 1, 1, None, None);
-    let croco_layout_handle = texture_atlas_layouts.add(croco_layout);
-
-    let textures = EnemyTextures{
-        eagle: EnemyTexture { image: eagle_texture.clone(), layout: eagle_layout_handle.clone(), animation: AnimationConfig::new(0, 0, 1) },
-        lion: EnemyTexture { image: lion_texture.clone(), layout: lion_layout_handle.clone(), animation: AnimationConfig::new(0, 0, 1) },
-        croco: EnemyTexture { image: croco_texture.clone(), layout: croco_layout_handle.clone(), animation: AnimationConfig::new(0, 0, 1) },
+    commands.insert_resource(EnemyDefinitionsHandle(asset_server.load(ENEMY_DEFINITIONS_FILE)));
+    commands.insert_resource(SpawnTableHandle(asset_server.load(&curve.spawn_table)));
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn build_roster(
+    mut commands: Commands,
+    handle: Res<EnemyDefinitionsHandle>,
+    table_handle: Res<SpawnTableHandle>,
+    definitions: Res<Assets<EnemyDefinitions>>,
+    tables: Res<Assets<SpawnTable>>,
+    mut loader: Option<SpritesheetLoader>,
+    mut failures: MessageReader<AssetLoadFailedEvent<EnemyDefinitions>>,
+    mut table_failures: MessageReader<AssetLoadFailedEvent<SpawnTable>>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    for failure in failures.read() {
+        error!("Could not load the enemies from {}: {}", failure.path, failure.error);
+        exit.write(AppExit::error());
+    }
+    for failure in table_failures.read() {
+        error!("Could not load the spawn table from {}: {}", failure.path, failure.error);
+        exit.write(AppExit::error());
+    }
+
+    let (Some(definitions), Some(table)) = (definitions.get(&handle.0), tables.get(&table_handle.0)) else {
+        return;
     };
 
-    commands.insert_resource(textures.clone());
+    // Only known once both are loaded
+    let spawn_plan = match table.plan(&definitions.0) {
+        Ok(spawn_plan) => spawn_plan,
+        Err(error) => {
+            error!("The spawn table does not fit the enemies: {}", error);
+            exit.write(AppExit::error());
+            return;
+        }
+    };
 
-    spawn_enemy(&mut commands, ENEMY_INITIAL_X, &textures);
+    let clips = definitions.0.iter()
+        .map(|definition| enemy_clips(&mut loader, definition))
+        .collect();
 
+    commands.insert_resource(EnemyRoster {
+        definitions: definitions.0.clone(),
+        spawn_plan,
+        clips,
+    });
 }
 
 
-fn spawn_enemy(
+// Picks a random pattern by the spawn weights at this distance, with the distance of each of
+// its enemies to the previous one. The pattern follows the previous one with the usual spacing.
+// Patterns which can't be cleared together with the enemies still in front of the player are
+// rolled again.
+fn choose_pattern(
+    rng: &mut StdRng,
+    roster: &EnemyRoster,
+    ahead: &[(f32, EnemyKind)],
+    curve: &DifficultyCurve,
+    velocity: f32,
+    distance: f32,
+) -> Vec<(EnemyKind, f32)> {
+    let (minimum_space, maximum_space) = curve.enemy_spacing(velocity);
+    let scale = curve.spacing_scale(velocity);
+    let arc = JumpArc::default();
+    let definitions = &roster.definitions;
+    let plan = &roster.spawn_plan;
+
+    let mut sequence: Vec<(f32, &EnemyDefinition)> = ahead.iter()
+        .map(|&(gap, kind)| (gap, &definitions[kind.0]))
+        .collect();
+
+    for _ in 0..ENEMY_GENERATION_ATTEMPTS {
+        // The spawn table makes sure there is a pattern and an enemy allowed at every distance
+        let pattern = plan.choose_pattern(rng, distance).expect("a pattern is allowed at every distance");
+        let lead = rng.random_range(minimum_space..maximum_space) + pattern.rest * scale;
+
+        let mut previous_offset = 0.;
+        let enemies: Vec<(EnemyKind, f32)> = pattern.enemies.iter().enumerate().map(|(index, &(kind, offset))| {
+            let kind = kind.unwrap_or_else(|| plan.choose_enemy(rng, distance).expect("an enemy is allowed at every distance"));
+            let gap = if index == 0 { lead } else { (offset - previous_offset) * scale };
+            previous_offset = offset;
+            (EnemyKind(kind), gap)
+        }).collect();
+
+        sequence.extend(enemies.iter().map(|&(kind, gap)| (gap, &definitions[kind.0])));
+        if is_clearable(&sequence, velocity, &arc) {
+            return enemies;
+        }
+        sequence.truncate(ahead.len());
+    }
+
+    // A single enemy after the widest gap leaves the most room to land before jumping again.
+    // If none of the allowed enemies fits there the gap is widened, an enemy a jump and a
+    // sprite further away than that no longer depends on the enemies ahead.
+    warn!("No clearable pattern found, falling back to a single enemy");
+    let allowed: Vec<usize> = (0..plan.enemies.len())
+        .filter(|&kind| plan.enemies[kind].weight(distance) > 0.)
+        .collect();
+    let widest = maximum_space + arc.jump_length(velocity) + SCALED_TILE_SIZE;
+    let mut gap = maximum_space;
+
+    loop {
+        for &kind in &allowed {
+            sequence.push((gap, &definitions[kind]));
+            if is_clearable(&sequence, velocity, &arc) {
+                return vec![(EnemyKind(kind), gap)];
+            }
+            sequence.pop();
+        }
+
+        if gap >= widest {
+            warn!("No enemy fits behind the enemies ahead");
+            return vec![(EnemyKind(allowed.first().copied().unwrap_or_default()), gap)];
+        }
+        gap = (gap + SCALED_TILE_SIZE / 2.).min(widest);
+    }
+}
+
+
+// The enemies of a pattern from the given position on
+fn spawn_pattern(
     commands: &mut Commands,
     x: f32,
-    textures: &EnemyTextures
+    pattern: &[(EnemyKind, f32)],
+    roster: &EnemyRoster,
 ) {
-    // Randomly choose an enemy type
-    let mut rng = rand::rng();
-    let enemy_type = match rng.random_range(0..3) {
-        0 => EnemyType::Eagle,
-        1 => EnemyType::Lion,
-        _ => EnemyType::Croco,
-    };
-    
-    let y = match enemy_type {
-        EnemyType::Eagle => ENEMY_FLYING_Y,
-        _ => ENEMY_WALKING_Y
-    };
-    
-    let enemy_texture = match enemy_type {
-        EnemyType::Eagle => textures.eagle.clone(),
-        EnemyType::Lion => textures.lion.clone(),
-        EnemyType::Croco => textures.croco.clone(),
-    };
+    let mut x = x;
 
-    let enemy_distance = rng.random_range(ENEMY_MINIMUM_SPACE..ENEMY_MAXIMUM_SPACE);
+    for &(kind, gap) in pattern {
+        x += gap;
+        spawn_enemy(commands, x, kind, roster);
+    }
+}
+
+
+fn spawn_enemy(
+    commands: &mut Commands,
+    x: f32,
+    kind: EnemyKind,
+    roster: &EnemyRoster,
+) {
+    let definition = roster.definition(kind);
 
     commands.spawn((
-        Sprite{
-            image: enemy_texture.image.clone(),
-            texture_atlas: Some(TextureAtlas { layout: enemy_texture.layout.clone(), index: 0 }),
-            ..default()
-        },
-        Transform::from_xyz(x + enemy_distance, y, 0.).with_scale(Vec3::splat(TILE_SCALE)),
+        Sprite::default(),
+        Transform::from_xyz(x, definition.lane_y(), 0.).with_scale(Vec3::splat(TILE_SCALE)),
         EnemySprite,
-        enemy_type,
-        enemy_texture.animation.clone()
+        kind,
+        definition.hitbox(),
+        roster.clips[kind.0].clone(),
+        SpriteAnimation::new(ENEMY_CLIP),
     ));
 }
 
 
-// This system loops through all the sprites in the `TextureAtlas`
-fn execute_animations(
+fn move_enemy(
+    mut commands: Commands,
     time: Res<Time>,
-    mut query: Query<(&mut AnimationConfig, &mut Sprite), With<EnemySprite>>,
+    mut game: ResMut<GameData>,
+    curve: Res<DifficultyCurve>,
+    mut rng: ResMut<WorldRng>,
+    roster: Res<EnemyRoster>,
+    mut query: Query<(Entity, &mut Transform, &EnemyKind), With<EnemySprite>>,
 ) {
-    for (mut config, mut sprite) in &mut query {
-
-        // Animate enemy
-        config.frame_timer.tick(time.delta());
-        if config.frame_timer.just_finished() {
-            if let Some(atlas) = &mut sprite.texture_atlas {
-                if atlas.index >= config.last_sprite_index {
-                    atlas.index = config.first_sprite_index;
-                } else {
-                    atlas.index += 1;
-                }
-            }
-            config.frame_timer = AnimationConfig::timer_from_fps(config.fps);
+    let move_distance = game.velocity * time.delta_secs();
+    let left_edge = -WINDOW_WIDTH / 2.0 - SCALED_TILE_SIZE;
+    let right_edge = WINDOW_WIDTH / 2.0;
+    let jumped_x = PLAYER_X - SCALED_TILE_SIZE;
+    let mut rightmost_x = f32::MIN;
+    let mut ahead = Vec::new();
+
+    for (entity, mut transform, kind) in &mut query {
+        // Move tile to the left
+        let previous_x = transform.translation.x;
+        transform.translation.x -= move_distance;
+
+        // Once an enemy is behind the player it can't hit anymore
+        if previous_x >= jumped_x && transform.translation.x < jumped_x {
+            game.enemies_jumped += 1;
         }
-    }
-}
 
+        if transform.translation.x >= jumped_x {
+            ahead.push((transform.translation.x, *kind));
+        }
 
-fn move_enemy(
-    mut commands: Commands,
-    time: Res<Time>,
-    game: Res<GameData>,
-    textures: Res<EnemyTextures>,
-    mut query: Query<(Entity, &mut Transform), With<EnemySprite>>,
-) {
-    match game.game_state {
-        GameState::Running => {
-            let move_distance = game.velocity * time.delta_secs();
-            let left_edge = -WINDOW_WIDTH / 2.0 - SCALED_TILE_SIZE;
-            let right_edge = WINDOW_WIDTH / 2.0;
-            let mut rightmost_x = f32::MIN;
-
-            for (entity, mut transform) in &mut query {
-                // Move tile to the left
-                transform.translation.x -= move_distance;
-
-                // Track the rightmost enemy position
-                if transform.translation.x > rightmost_x {
-                    rightmost_x = transform.translation.x;
-                }
-
-                // If tile has moved off the left edge, despawn it
-                if transform.translation.x < left_edge {
-                    commands.entity(entity).despawn();
-                }
-            }
+        // Track the rightmost enemy position
+        if transform.translation.x > rightmost_x {
+            rightmost_x = transform.translation.x;
+        }
 
-            // Check if we need to spawn a new tile on the right
-            // Spawn when the rightmost tile has moved far enough left to leave a gap
-            if rightmost_x < right_edge - SCALED_TILE_SIZE / 2. {
-                
-                spawn_enemy(&mut commands, rightmost_x, &textures);
-            }
+        // If tile has moved off the left edge, despawn it
+        if transform.translation.x < left_edge {
+            commands.entity(entity).despawn();
         }
-        _ => {}
+    }
+
+    // The next pattern is spawned when the last enemy has moved far enough left to leave a gap
+    if rightmost_x < right_edge - SCALED_TILE_SIZE / 2. {
+        // The solvability check works on the gaps between the enemies
+        ahead.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut previous_x = ahead.first().map_or(0., |enemy| enemy.0);
+        let gaps: Vec<(f32, EnemyKind)> = ahead.iter().map(|&(x, kind)| {
+            let gap = x - previous_x;
+            previous_x = x;
+            (gap, kind)
+        }).collect();
+
+        let pattern = choose_pattern(&mut rng.enemies, &roster, &gaps, &curve, game.velocity, game.current_score);
+        spawn_pattern(&mut commands, rightmost_x, &pattern, &roster);
     }
 }
 
 
-fn handle_enemy_reset(
-    _evt: On<GameReset>,
+fn despawn_enemies(
     mut commands: Commands,
-    textures: Res<EnemyTextures>,
-    enemy_query: Query<(Entity), With<EnemySprite>>,
+    enemy_query: Query<Entity, With<EnemySprite>>,
 )
 {
     for enemy_entity in & enemy_query {
 
         commands.entity(enemy_entity).despawn(); 
     }
+}
 
-    spawn_enemy(&mut commands, ENEMY_INITIAL_X, &textures);
+
+fn spawn_first_pattern(
+    mut commands: Commands,
+    curve: Res<DifficultyCurve>,
+    mut rng: ResMut<WorldRng>,
+    roster: Res<EnemyRoster>,
+)
+{
+    let pattern = choose_pattern(&mut rng.enemies, &roster, &[], &curve, curve.initial_velocity, 0.);
+    spawn_pattern(&mut commands, ENEMY_INITIAL_X, &pattern, &roster);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::enemy_definitions::EnemyDefinitions;
+
+    // Eagles are the only enemy spawned, one at a time
+    fn eagle_roster() -> EnemyRoster {
+        let definitions = EnemyDefinitions::parse(include_bytes!("../assets/enemies/default.enemies.ron")).unwrap().0;
+        let table = SpawnTable::parse(br#"(
+            enemies: { "Eagle": [(from: 0.0, weight: 1.0)] },
+            patterns: [(name: "Single", weight: [(from: 0.0, weight: 1.0)], enemies: [(offset: 0.0)])],
+        )"#).unwrap();
+
+        EnemyRoster {
+            spawn_plan: table.plan(&definitions).unwrap(),
+            clips: vec![SpriteClips::default(); definitions.len()],
+            definitions,
+        }
+    }
+
+    #[test]
+    fn fallback_widens_the_gap_until_an_enemy_fits() {
+        let roster = eagle_roster();
+        let lion = EnemyKind(roster.definitions.iter().position(|definition| definition.name == "Lion").unwrap());
+        // At a tenth of the initial velocity the spacing is too short to land between a lion and an eagle
+        let curve = DifficultyCurve { initial_velocity: INITIAL_VELOCITY * 10., ..default() };
+        let (_, maximum_space) = curve.enemy_spacing(INITIAL_VELOCITY);
+        let ahead = [(0., lion)];
+
+        let pattern = choose_pattern(&mut StdRng::seed_from_u64(0), &roster, &ahead, &curve, INITIAL_VELOCITY, 0.);
+
+        assert_eq!(pattern.len(), 1);
+        let (kind, gap) = pattern[0];
+        assert_eq!(roster.definition(kind).name, "Eagle");
+        assert!(gap > maximum_space, "the gap of {} was not widened", gap);
+        let sequence = [(0., roster.definition(lion)), (gap, roster.definition(kind))];
+        assert!(is_clearable(&sequence, INITIAL_VELOCITY, &JumpArc::default()));
+    }
 }
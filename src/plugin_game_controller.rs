@@ -1,33 +1,128 @@
+use std::time::Duration;
 use bevy::prelude::*;
 
-use crate::model::{GameData, GameEnd, GameStart, GameState};
+use crate::constants::RESTART_LOCK_SECS;
+use crate::model::{DifficultyCurve, GameData, GameEnd, GamePause, GameReset, GameResume, GameStart, GameState, GameSystems, world_running};
+use crate::plugin_enemy::EnemyRoster;
 
 pub struct GameController;
 
 impl Plugin for GameController {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_distance)
+        app.insert_resource(RestartLock(Timer::default()))
+           .add_systems(FixedUpdate, (update_distance, update_velocity).chain()
+                .in_set(GameSystems::Progress)
+                .run_if(world_running))
+           .add_systems(Update, finish_loading.run_if(in_state(GameState::Loading).and(resource_exists::<EnemyRoster>)))
+           .add_systems(Update, tick_restart_lock.run_if(in_state(GameState::Dead)))
+           .add_systems(OnEnter(GameState::Dead), lock_restart)
+           .add_systems(OnEnter(GameState::Reset), finish_reset)
            .add_observer(handle_game_start)
-           .add_observer(handle_game_end);
+           .add_observer(handle_game_end)
+           .add_observer(handle_game_pause)
+           .add_observer(handle_game_resume)
+           .add_observer(handle_game_reset);
     }
 }
 
 
+// Keeps a press which was meant as a jump from skipping the death screen
+#[derive(Resource)]
+pub struct RestartLock(pub Timer);
+
+
+// Only the enemies have to be loaded to generate a run, all other textures may still
+// be loading while waiting for the start
+fn finish_loading(
+    mut next_state: ResMut<NextState<GameState>>
+)
+{
+    next_state.set(GameState::WaitingForStart);
+}
+
+
 fn handle_game_start(
     _evt: On<GameStart>,
-    mut game: ResMut<GameData>
+    mut next_state: ResMut<NextState<GameState>>
 )
 {
-    game.game_state = GameState::Running;
+    next_state.set(GameState::Running);
 }
 
 
 fn handle_game_end(
-    _evt: On<GameEnd>,
-    mut game: ResMut<GameData>
+    evt: On<GameEnd>,
+    mut game: ResMut<GameData>,
+    mut next_state: ResMut<NextState<GameState>>
+)
+{
+    game.killed_by = Some(evt.killed_by.clone());
+    next_state.set(GameState::Dead);
+}
+
+
+// Only a running game can be paused, a player who was just hit dies first
+fn handle_game_pause(
+    _evt: On<GamePause>,
+    state: Res<State<GameState>>,
+    game: Res<GameData>,
+    mut next_state: ResMut<NextState<GameState>>
+)
+{
+    if *state.get() == GameState::Running && game.killed_by.is_none() {
+        next_state.set(GameState::Paused);
+    }
+}
+
+
+fn handle_game_resume(
+    _evt: On<GameResume>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>
+)
+{
+    if *state.get() == GameState::Paused {
+        next_state.set(GameState::Running);
+    }
+}
+
+
+fn handle_game_reset(
+    _evt: On<GameReset>,
+    lock: Res<RestartLock>,
+    mut next_state: ResMut<NextState<GameState>>
+)
+{
+    if lock.0.is_finished() {
+        next_state.set(GameState::Reset);
+    }
+}
+
+
+fn lock_restart(
+    mut lock: ResMut<RestartLock>
+)
+{
+    lock.0 = Timer::new(Duration::from_secs_f32(RESTART_LOCK_SECS), TimerMode::Once);
+}
+
+
+fn tick_restart_lock(
+    time: Res<Time>,
+    mut lock: ResMut<RestartLock>
 )
 {
-    game.game_state = GameState::Dead;
+    lock.0.tick(time.delta());
+}
+
+
+// All plugins tear down and respawn their entities in OnEnter(Reset),
+// afterwards the game waits for the player to start again
+fn finish_reset(
+    mut next_state: ResMut<NextState<GameState>>
+)
+{
+    next_state.set(GameState::WaitingForStart);
 }
 
 
@@ -36,11 +131,15 @@ fn update_distance(
     mut game: ResMut<GameData>
 )
 {
-    match game.game_state {
-        GameState::Running => {
-            let move_distance = game.velocity * time.delta_secs();
-            game.current_score += move_distance;
-        }
-        _ => {}
-    }
+    let move_distance = game.velocity * time.delta_secs();
+    game.current_score += move_distance;
+}
+
+
+fn update_velocity(
+    curve: Res<DifficultyCurve>,
+    mut game: ResMut<GameData>
+)
+{
+    game.velocity = curve.velocity(game.current_score);
 }
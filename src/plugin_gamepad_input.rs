@@ -0,0 +1,44 @@
+//! Gamepad input plugin module
+//!
+//! Every connected gamepad can play, so controllers can be plugged in and out at any time.
+
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::prelude::*;
+
+use crate::plugin_actions::{ActionSystems, Actions, Bindings, queue_actions};
+
+
+pub struct GamepadInputPlugin;
+
+impl Plugin for GamepadInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, log_connections)
+           .add_systems(PreUpdate, handle_input.in_set(ActionSystems));
+    }
+}
+
+
+fn log_connections(
+    mut connections: MessageReader<GamepadConnectionEvent>,
+) {
+    for connection in connections.read() {
+        match &connection.connection {
+            GamepadConnection::Connected { name, .. } => info!("Gamepad {} connected", name),
+            GamepadConnection::Disconnected => info!("Gamepad {} disconnected", connection.gamepad),
+        }
+    }
+}
+
+
+fn handle_input(
+    mut actions: ResMut<Actions>,
+    gamepads: Query<&Gamepad>,
+    bindings: Res<Bindings>,
+) {
+    queue_actions(
+        &mut actions,
+        &bindings.gamepad,
+        |button| gamepads.iter().any(|gamepad| gamepad.just_pressed(*button)),
+        |button| gamepads.iter().any(|gamepad| gamepad.just_released(*button)),
+    );
+}
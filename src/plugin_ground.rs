@@ -1,71 +1,144 @@
 //! Ground plugin module
+//!
+//! Water tiles are gaps, a player running onto one has to jump before the coyote time runs
+//! out or drowns. The generator keeps every stretch of water jumpable on its own.
 
 use bevy::prelude::*;
 use rand::Rng;
+use rand::rngs::StdRng;
 
 use crate::constants::*;
 use crate::model::*;
+use crate::plugin_enemy::{EnemyMovement, EnemySprite};
+use crate::plugin_player::{Jump, PlayerSprite};
+use crate::plugin_spritesheets::SpritesheetLoader;
+use crate::solvability::JumpArc;
 
 pub struct GroundPlugin;
 
 impl Plugin for GroundPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_ground)
-           .add_systems(FixedUpdate, move_ground);
+        app.init_resource::<GroundRun>()
+           .add_systems(Startup, setup_ground)
+           .add_systems(FixedUpdate, (
+                // Water is placed by the enemies spawned so far, which have to be moved first
+                (move_ground.after(EnemyMovement), move_tombstone).in_set(GameSystems::Movement),
+                support_player.in_set(GameSystems::Collision),
+            ).run_if(world_running))
+           .add_systems(OnEnter(GameState::Reset), (reset_ground, despawn_tombstone))
+           .add_systems(OnEnter(GameState::WaitingForStart), setup_tombstone.run_if(resource_exists::<GroundTextures>));
     }
 }
 
 
 #[derive(Component, Debug, Clone, Copy, PartialEq)]
-enum GroundType {
+pub enum GroundType {
     Dirt,
     Grass,
     Water,
 }
 
 #[derive(Component)]
-struct GroundTile;
+pub struct GroundTile;
 
-#[derive(Resource)]
+// Tiles since the ground last changed between dry and water
+#[derive(Resource, Default)]
+struct GroundRun {
+    water: usize,
+    dry: usize,
+}
+
+#[derive(Component)]
+struct Tombstone;
+
+// Each texture with its layout
+#[derive(Resource, Default)]
 struct GroundTextures {
-    dirt: Handle<Image>,
-    grass: Handle<Image>,
-    water: Handle<Image>,
-    tombstone: Handle<Image>,
-    layout: Handle<TextureAtlasLayout>,
+    dirt: (Handle<Image>, Handle<TextureAtlasLayout>),
+    grass: (Handle<Image>, Handle<TextureAtlasLayout>),
+    water: (Handle<Image>, Handle<TextureAtlasLayout>),
+    tombstone: (Handle<Image>, Handle<TextureAtlasLayout>),
+}
+
+impl GroundTextures {
+    fn load(loader: &mut SpritesheetLoader) -> Self {
+        Self {
+            dirt: loader.load("textures/ground/dirt_top.png", SPRITE_FRAME_SIZE),
+            grass: loader.load("textures/ground/grass_top.png", SPRITE_FRAME_SIZE),
+            water: loader.load("textures/ground/water_top.png", SPRITE_FRAME_SIZE),
+            tombstone: loader.load("textures/ground/rip.png", SPRITE_FRAME_SIZE),
+        }
+    }
 }
 
 fn setup_ground(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut run: ResMut<GroundRun>,
+    loader: Option<SpritesheetLoader>,
 ) {
-    // Load ground textures
-    let dirt_texture = asset_server.load("textures/ground/dirt_top.png");
-    let grass_texture = asset_server.load("textures/ground/grass_top.png");
-    let water_texture = asset_server.load("textures/ground/water_top.png");
-    let tombstone_texture = asset_server.load("textures/ground/rip.png");
-    
-    // Create texture atlas layout (1 sprite, 240x240)
-    let layout = TextureAtlasLayout::from_grid(UVec2::splat(240), 1, 1, None, None);
-    let layout_handle = texture_atlas_layouts.add(layout);
- 
+    let textures = match loader {
+        Some(mut loader) => GroundTextures::load(&mut loader),
+        None => GroundTextures::default(),
+    };
+
+    spawn_initial_ground(&mut commands, &mut run, &textures);
+
     // Store textures as a resource for spawning new tiles
-    commands.insert_resource(GroundTextures {
-        dirt: dirt_texture.clone(),
-        grass: grass_texture.clone(),
-        water: water_texture.clone(),
-        tombstone: tombstone_texture.clone(),
-        layout: layout_handle.clone(),
-    });
+    commands.insert_resource(textures);
+}
+
 
+// The run starts on dry ground, there is no water on the first screen
+fn spawn_initial_ground(
+    commands: &mut Commands,
+    run: &mut GroundRun,
+    textures: &GroundTextures,
+) {
     // Calculate how many tiles we need to fill the screen + 1 extra
     let tiles_needed = (WINDOW_WIDTH / SCALED_TILE_SIZE).ceil() as usize + 1;
 
-    // Spawn initial tiles from left to right
+    // Spawn initial tiles from left to right, alternating so the ground does not look flat
     for i in 0..tiles_needed {
         let x = -WINDOW_WIDTH / 2.0 + (i as f32 * SCALED_TILE_SIZE);
-        spawn_ground_tile(&mut commands, x, &dirt_texture, &grass_texture, &water_texture, &layout_handle);
+        let ground_type = if i % 2 == 0 { GroundType::Dirt } else { GroundType::Grass };
+        spawn_ground_tile(commands, x, ground_type, textures);
+    }
+
+    *run = GroundRun { water: 0, dry: tiles_needed };
+}
+
+
+// Water is only allowed where it can be jumped on its own: a stretch is shorter than a jump,
+// there is room to land and take off again between two stretches, and no enemy is close
+fn choose_ground_type(
+    rng: &mut StdRng,
+    run: &mut GroundRun,
+    velocity: f32,
+    enemy_near: bool,
+) -> GroundType {
+    let jump_length = JumpArc::default().jump_length(velocity);
+    let longest_water = ((jump_length - SCALED_TILE_SIZE / 2.) / SCALED_TILE_SIZE).floor() as usize;
+    let shortest_dry = (jump_length / SCALED_TILE_SIZE).ceil() as usize + 1;
+
+    let water_allowed = !enemy_near && if run.water > 0 {
+        run.water < longest_water
+    } else {
+        run.dry >= shortest_dry
+    };
+
+    if water_allowed && rng.random_bool(WATER_CHANCE) {
+        run.water += 1;
+        run.dry = 0;
+        return GroundType::Water;
+    }
+
+    run.water = 0;
+    run.dry += 1;
+
+    if rng.random_bool(0.5) {
+        GroundType::Dirt
+    } else {
+        GroundType::Grass
     }
 }
 
@@ -73,30 +146,20 @@ fn setup_ground(
 fn spawn_ground_tile(
     commands: &mut Commands,
     x: f32,
-    dirt_texture: &Handle<Image>,
-    grass_texture: &Handle<Image>,
-    water_texture: &Handle<Image>,
-    layout: &Handle<TextureAtlasLayout>,
+    ground_type: GroundType,
+    textures: &GroundTextures,
 ) {
-    // Randomly choose a ground type
-    let mut rng = rand::rng();
-    let ground_type = match rng.random_range(0..3) {
-        0 => GroundType::Dirt,
-        1 => GroundType::Grass,
-        _ => GroundType::Water,
-    };
-
-    let texture = match ground_type {
-        GroundType::Dirt => dirt_texture.clone(),
-        GroundType::Grass => grass_texture.clone(),
-        GroundType::Water => water_texture.clone(),
+    let (texture, layout) = match ground_type {
+        GroundType::Dirt => textures.dirt.clone(),
+        GroundType::Grass => textures.grass.clone(),
+        GroundType::Water => textures.water.clone(),
     };
 
     commands.spawn((
         Sprite {
             image: texture,
             texture_atlas: Some(TextureAtlas {
-                layout: layout.clone(),
+                layout,
                 index: 0,
             }),
             ..default()
@@ -121,56 +184,136 @@ fn spawn_tombstone(
             image: texture,
             texture_atlas: Some(TextureAtlas { layout: layout.clone(), index: 0 }), ..default()
         },
-        Transform::from_xyz(x, PLAYER_GROUND, -1.0).with_scale(Vec3::splat((TILE_SCALE))),
-        GroundTile
+        Transform::from_xyz(x, PLAYER_GROUND, -1.0).with_scale(Vec3::splat(TILE_SCALE)),
+        Tombstone
     ));
 }
 
 
+#[allow(clippy::too_many_arguments)]
 fn move_ground(
     mut commands: Commands,
     time: Res<Time>,
     game: Res<GameData>,
+    curve: Res<DifficultyCurve>,
+    mut rng: ResMut<WorldRng>,
+    mut run: ResMut<GroundRun>,
     textures: Res<GroundTextures>,
     mut query: Query<(Entity, &mut Transform), With<GroundTile>>,
+    enemy_query: Query<&Transform, (With<EnemySprite>, Without<GroundTile>)>,
 ) {
-    match game.game_state {
-        GameState::Running => {
-            let move_distance = game.velocity * time.delta_secs();
-            let left_edge = -WINDOW_WIDTH / 2.0 - SCALED_TILE_SIZE;
-            let right_edge = WINDOW_WIDTH / 2.0;
-
-            let mut rightmost_x = f32::MIN;
-
-            for (entity, mut transform) in &mut query {
-                // Move tile to the left
-                transform.translation.x -= move_distance;
-
-                // Track the rightmost tile position
-                if transform.translation.x > rightmost_x {
-                    rightmost_x = transform.translation.x;
-                }
-
-                // If tile has moved off the left edge, despawn it
-                if transform.translation.x < left_edge {
-                    commands.entity(entity).despawn();
-                }
-            }
-
-            // Check if we need to spawn a new tile on the right
-            // Spawn when the rightmost tile has moved far enough left to leave a gap
-            if rightmost_x < right_edge - SCALED_TILE_SIZE / 2. {
-                let new_x = rightmost_x + SCALED_TILE_SIZE;
-                spawn_ground_tile(
-                    &mut commands,
-                    new_x,
-                    &textures.dirt,
-                    &textures.grass,
-                    &textures.water,
-                    &textures.layout,
-                );
-            }
+    let move_distance = game.velocity * time.delta_secs();
+    let left_edge = -WINDOW_WIDTH / 2.0 - SCALED_TILE_SIZE;
+    let right_edge = WINDOW_WIDTH / 2.0;
+
+    let mut rightmost_x = f32::MIN;
+
+    for (entity, mut transform) in &mut query {
+        // Move tile to the left
+        transform.translation.x -= move_distance;
+
+        // Track the rightmost tile position
+        if transform.translation.x > rightmost_x {
+            rightmost_x = transform.translation.x;
+        }
+
+        // If tile has moved off the left edge, despawn it
+        if transform.translation.x < left_edge {
+            commands.entity(entity).despawn();
         }
-        _ => {}
     }
-}
\ No newline at end of file
+
+    // Check if we need to spawn a new tile on the right
+    // Spawn when the rightmost tile has moved far enough left to leave a gap
+    if rightmost_x < right_edge - SCALED_TILE_SIZE / 2. {
+        let new_x = rightmost_x + SCALED_TILE_SIZE;
+
+        // Enemies are spawned further ahead than the ground, the next one comes at least
+        // the minimum spacing after the rightmost
+        let clearance = JumpArc::default().jump_length(game.velocity) + SCALED_TILE_SIZE;
+        let rightmost_enemy = enemy_query.iter().map(|transform| transform.translation.x).fold(f32::MIN, f32::max);
+        let next_enemy = rightmost_enemy + curve.enemy_spacing(game.velocity).0;
+        let enemy_near = next_enemy - new_x < clearance
+            || enemy_query.iter().any(|transform| (transform.translation.x - new_x).abs() < clearance);
+
+        let ground_type = choose_ground_type(&mut rng.ground, &mut run, game.velocity, enemy_near);
+        spawn_ground_tile(&mut commands, new_x, ground_type, &textures);
+    }
+}
+
+
+// The player stands on the tile below its center
+fn support_player(
+    mut jump: Single<&mut Jump, With<PlayerSprite>>,
+    query: Query<(&Transform, &GroundType), With<GroundTile>>,
+) {
+    let half_tile = SCALED_TILE_SIZE / 2.;
+    let supported = query.iter()
+        .find(|(transform, _)| (transform.translation.x - half_tile..transform.translation.x + half_tile).contains(&PLAYER_X))
+        .is_none_or(|(_, ground_type)| *ground_type != GroundType::Water);
+
+    if jump.supported != supported {
+        jump.supported = supported;
+    }
+}
+
+
+// The tombstone is placed at the distance of the last death, the player reaches it when
+// the current score equals that distance
+fn setup_tombstone(
+    mut commands: Commands,
+    game: Res<GameData>,
+    textures: Res<GroundTextures>,
+)
+{
+    if let Some(distance) = game.tombstone_distance {
+        let x = PLAYER_X + distance - game.current_score;
+        spawn_tombstone(&mut commands, x, &textures.tombstone.0, &textures.tombstone.1);
+    }
+}
+
+
+fn move_tombstone(
+    mut commands: Commands,
+    time: Res<Time>,
+    game: Res<GameData>,
+    mut query: Query<(Entity, &mut Transform), With<Tombstone>>,
+) {
+    let move_distance = game.velocity * time.delta_secs();
+    let left_edge = -WINDOW_WIDTH / 2.0 - SCALED_TILE_SIZE;
+
+    for (entity, mut transform) in &mut query {
+        transform.translation.x -= move_distance;
+
+        if transform.translation.x < left_edge {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+
+// The ground is generated again so a run only depends on its seed
+fn reset_ground(
+    mut commands: Commands,
+    mut run: ResMut<GroundRun>,
+    textures: Res<GroundTextures>,
+    query: Query<Entity, With<GroundTile>>,
+)
+{
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+
+    spawn_initial_ground(&mut commands, &mut run, &textures);
+}
+
+
+fn despawn_tombstone(
+    mut commands: Commands,
+    query: Query<Entity, With<Tombstone>>,
+)
+{
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,217 @@
+//! High score plugin module
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::model::{GameData, GameState, KilledBy, WorldRng, today};
+
+pub struct HighscorePlugin;
+
+impl Plugin for HighscorePlugin {
+    fn build(&self, app: &mut App) {
+        // Loaded right away, the start screen is shown before Startup runs
+        app.insert_resource(Highscores::load())
+           .init_resource::<RecordHighscores>()
+           .add_systems(OnEnter(GameState::Dead), record_highscore.run_if(resource_equals(RecordHighscores(true))));
+    }
+}
+
+
+// Runs nobody played, like the autopilot's or replays, don't go into the table
+#[derive(Resource, PartialEq)]
+pub struct RecordHighscores(pub bool);
+
+
+impl Default for RecordHighscores {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HighscoreEntry {
+    pub distance: f32,
+    pub date: String,
+    pub killed_by: Option<KilledBy>,
+}
+
+
+#[derive(Resource, Default)]
+pub struct Highscores {
+    pub entries: Vec<HighscoreEntry>,
+    // Rank of the last run, None if it did not make it into the table
+    pub last_rank: Option<usize>,
+    // Best distance of the daily course per date
+    pub daily_bests: BTreeMap<String, f32>,
+    pub last_was_daily_best: bool,
+}
+
+
+fn path(file: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(HIGHSCORE_DIRECTORY).join(file))
+}
+
+
+fn load_file<T: DeserializeOwned + Default>(file: &str) -> T {
+    let Some(path) = path(file) else {
+        return T::default();
+    };
+
+    // A missing file just means nobody played yet
+    let Ok(content) = fs::read_to_string(&path) else {
+        return T::default();
+    };
+
+    ron::from_str(&content).unwrap_or_else(|err| {
+        warn!("Ignoring corrupt high score file {}: {}", path.display(), err);
+        T::default()
+    })
+}
+
+
+fn save_file<T: Serialize>(file: &str, value: &T) {
+    let Some(path) = path(file) else {
+        return;
+    };
+
+    if let Some(directory) = path.parent()
+        && let Err(err) = fs::create_dir_all(directory)
+    {
+        warn!("Could not create {}: {}", directory.display(), err);
+        return;
+    }
+
+    match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(content) => {
+            if let Err(err) = fs::write(&path, content) {
+                warn!("Could not write high scores to {}: {}", path.display(), err);
+            }
+        }
+        Err(err) => warn!("Could not serialize high scores: {}", err),
+    }
+}
+
+
+impl Highscores {
+    fn load() -> Self {
+        let mut entries: Vec<HighscoreEntry> = load_file(HIGHSCORE_FILE);
+        entries.sort_by(|a, b| b.distance.total_cmp(&a.distance));
+        entries.truncate(HIGHSCORE_COUNT);
+
+        Self {
+            entries,
+            last_rank: None,
+            daily_bests: load_file(DAILY_BEST_FILE),
+            last_was_daily_best: false,
+        }
+    }
+
+    pub fn daily_best(&self, date: &str) -> Option<f32> {
+        self.daily_bests.get(date).copied()
+    }
+
+    // Returns whether the distance is a new best for the date
+    fn insert_daily(&mut self, date: &str, distance: f32) -> bool {
+        if self.daily_best(date).is_some_and(|best| best >= distance) {
+            return false;
+        }
+
+        self.daily_bests.insert(date.to_string(), distance);
+        true
+    }
+
+    // Inserts the entry and returns its rank, None if it is not good enough for the table
+    fn insert(&mut self, entry: HighscoreEntry) -> Option<usize> {
+        let rank = self.entries.iter()
+            .position(|existing| entry.distance > existing.distance)
+            .unwrap_or(self.entries.len());
+
+        if rank >= HIGHSCORE_COUNT {
+            return None;
+        }
+
+        self.entries.insert(rank, entry);
+        self.entries.truncate(HIGHSCORE_COUNT);
+        Some(rank)
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.entries.iter().enumerate().map(|(rank, entry)| {
+            let killed_by = entry.killed_by.as_ref().map_or("Unknown", |killed_by| killed_by.name());
+            format!("{}. {} - {} - {}", rank + 1, entry.distance as usize, entry.date, killed_by)
+        }).collect()
+    }
+}
+
+
+// Daily runs only count for the best of their date, all other runs go into the table
+pub fn record_highscore(
+    game: Res<GameData>,
+    rng: Res<WorldRng>,
+    mut highscores: ResMut<Highscores>,
+)
+{
+    highscores.last_rank = None;
+    highscores.last_was_daily_best = false;
+
+    if let Some(date) = &rng.daily_date {
+        highscores.last_was_daily_best = highscores.insert_daily(date, game.current_score);
+
+        if highscores.last_was_daily_best {
+            save_file(DAILY_BEST_FILE, &highscores.daily_bests);
+        }
+        return;
+    }
+
+    let entry = HighscoreEntry {
+        distance: game.current_score,
+        date: today(),
+        killed_by: game.killed_by.clone(),
+    };
+
+    highscores.last_rank = highscores.insert(entry);
+
+    if highscores.last_rank.is_some() {
+        save_file(HIGHSCORE_FILE, &highscores.entries);
+    }
+}
+
+
+// Adds the high score table to a screen
+pub fn spawn_highscore_table(
+    parent: &mut ChildSpawnerCommands,
+    highscores: &Highscores,
+)
+{
+    if highscores.entries.is_empty() {
+        return;
+    }
+
+    parent.spawn((
+        Text::new("High scores"),
+        TextFont {
+            font_size: SCREEN_TEXT_FONT_SIZE,
+            ..default()
+        },
+        TextColor(SCREEN_TITLE_COLOR),
+    ));
+
+    for (rank, line) in highscores.lines().into_iter().enumerate() {
+        let color = if highscores.last_rank == Some(rank) { SCREEN_TITLE_COLOR } else { SCREEN_TEXT_COLOR };
+
+        parent.spawn((
+            Text::new(line),
+            TextFont {
+                font_size: HIGHSCORE_FONT_SIZE,
+                ..default()
+            },
+            TextColor(color),
+        ));
+    }
+}
@@ -0,0 +1,46 @@
+//! Hitbox debug plugin module, F1 toggles drawing the hitboxes
+
+use bevy::prelude::*;
+
+use crate::constants::HITBOX_COLOR;
+use crate::hitbox::Hitbox;
+
+pub struct HitboxDebugPlugin;
+
+impl Plugin for HitboxDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowHitboxes(false))
+           .add_systems(Update, (
+                toggle_hitboxes,
+                draw_hitboxes.run_if(|show: Res<ShowHitboxes>| show.0),
+            ));
+    }
+}
+
+
+#[derive(Resource)]
+struct ShowHitboxes(bool);
+
+
+fn toggle_hitboxes(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut show: ResMut<ShowHitboxes>,
+) {
+    if keyboard.just_pressed(KeyCode::F1) {
+        show.0 = !show.0;
+    }
+}
+
+
+fn draw_hitboxes(
+    mut gizmos: Gizmos,
+    query: Query<(&GlobalTransform, &Hitbox)>,
+) {
+    for (transform, hitbox) in &query {
+        let position = transform.translation().truncate();
+
+        for aabb in &hitbox.0 {
+            gizmos.rect_2d(position + aabb.offset, aabb.half_size * 2., HITBOX_COLOR);
+        }
+    }
+}
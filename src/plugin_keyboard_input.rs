@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::model::{GameData, GameStart, GameState, PlayerJump, GameReset};
+use crate::plugin_actions::{ActionSystems, Actions, Bindings, queue_actions};
 
 
 pub struct KeyboardInputPlugin;
@@ -8,32 +8,21 @@ pub struct KeyboardInputPlugin;
 impl Plugin for KeyboardInputPlugin {
     fn build(&self, app: &mut App) {
         app//.add_systems(Startup, setup_keyboard_input)
-           .add_systems(FixedUpdate, handle_input);
+           .add_systems(PreUpdate, handle_input.in_set(ActionSystems));
     }
 }
 
 
 
 fn handle_input(
-    mut commands: Commands,
+    mut actions: ResMut<Actions>,
     keyboard: Res<ButtonInput<KeyCode>>,
-    game: ResMut<GameData>
+    bindings: Res<Bindings>,
 ) {
-    if keyboard.just_pressed(KeyCode::Space){
-        match game.game_state {
-            GameState::WaitingForStart => {
-                commands.trigger(GameStart);
-            }
-
-            GameState::Running => {
-                commands.trigger(PlayerJump);
-            }
-
-            GameState::Dead => {
-                commands.trigger(GameReset);
-            }
-
-            _ => {}
-        }
+    queue_actions(
+        &mut actions,
+        &bindings.keyboard,
+        |key| keyboard.just_pressed(*key),
+        |key| keyboard.just_released(*key),
+    );
 }
-}
\ No newline at end of file
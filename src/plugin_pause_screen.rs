@@ -0,0 +1,129 @@
+//! Pause screen plugin module
+//!
+//! The game pauses on the pause action or when the window loses focus. The overlay has
+//! buttons to resume or quit, so it also works without a pause binding.
+
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+
+use crate::constants::*;
+use crate::model::{GamePause, GameResume, GameState};
+
+#[derive(Component)]
+struct PauseScreenUi;
+
+#[derive(Component, Clone, Copy)]
+enum PauseButton {
+    Resume,
+    Quit,
+}
+
+
+pub struct PauseScreenPlugin;
+
+impl Plugin for PauseScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Paused), setup_pause_screen)
+           .add_systems(Update, (
+                pause_on_focus_loss.run_if(in_state(GameState::Running)),
+                press_pause_buttons.run_if(in_state(GameState::Paused)),
+            ))
+           .add_systems(OnExit(GameState::Paused), despawn_pause_screen);
+    }
+}
+
+
+fn pause_button(parent: &mut ChildSpawnerCommands, label: &str, button: PauseButton) {
+    parent.spawn((
+        Button,
+        Node {
+            width: BUTTON_WIDTH,
+            padding: UiRect::all(BUTTON_PADDING),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(BUTTON_COLOR),
+        button,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new(label),
+            TextFont {
+                font_size: SCREEN_TEXT_FONT_SIZE,
+                ..default()
+            },
+            TextColor(SCREEN_TEXT_COLOR),
+        ));
+    });
+}
+
+
+fn setup_pause_screen(
+    mut commands: Commands,
+)
+{
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: SCREEN_ROW_GAP,
+            ..default()
+        },
+        BackgroundColor(SCREEN_OVERLAY_COLOR),
+        PauseScreenUi,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("Paused"),
+            TextFont {
+                font_size: SCREEN_TITLE_FONT_SIZE,
+                ..default()
+            },
+            TextColor(SCREEN_TITLE_COLOR),
+        ));
+
+        pause_button(parent, "Resume", PauseButton::Resume);
+        pause_button(parent, "Quit", PauseButton::Quit);
+    });
+}
+
+
+fn pause_on_focus_loss(
+    mut commands: Commands,
+    mut focus_events: MessageReader<WindowFocused>,
+)
+{
+    if focus_events.read().any(|event| !event.focused) {
+        commands.trigger(GamePause);
+    }
+}
+
+
+fn press_pause_buttons(
+    mut commands: Commands,
+    mut query: Query<(&Interaction, &PauseButton, &mut BackgroundColor), Changed<Interaction>>,
+)
+{
+    for (interaction, button, mut color) in &mut query {
+        match interaction {
+            Interaction::Pressed => match button {
+                PauseButton::Resume => commands.trigger(GameResume),
+                PauseButton::Quit => { commands.write_message(AppExit::Success); }
+            },
+            Interaction::Hovered => *color = BackgroundColor(BUTTON_HOVERED_COLOR),
+            Interaction::None => *color = BackgroundColor(BUTTON_COLOR),
+        }
+    }
+}
+
+
+fn despawn_pause_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<PauseScreenUi>>,
+)
+{
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
@@ -1,185 +1,256 @@
 //! Player plugin module
 
-use std::time::Duration;
 use bevy::prelude::*;
 
 use crate::constants::*;
-use crate::model::{GameData, GameEnd, GameReset, GameState, PlayerJump};
-use crate::plugin_enemy::EnemySprite;
+use crate::hitbox::{Hitbox, PLAYER_DUCKING_HITBOX, PLAYER_JUMPING_HITBOX, PLAYER_RUNNING_HITBOX};
+use crate::model::{GameData, GameEnd, GameState, GameSystems, KilledBy, PlayerDuck, PlayerJump, PlayerJumpReleased, PlayerStandUp, world_running};
+use crate::plugin_enemy::{EnemyKind, EnemyRoster, EnemySprite};
+use crate::plugin_sprite_animation::{ClipState, Playback, SpriteAnimation, SpriteAnimationSystems, SpriteClip, SpriteClips, play_state_clips};
+use crate::plugin_spritesheets::SpritesheetLoader;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_player)
-           .add_systems(Update, execute_animations)
-           .add_systems(FixedUpdate, (check_for_collisions, update_jump))
+           .add_systems(Update, play_state_clips::<PlayerState>.before(SpriteAnimationSystems))
+           .add_systems(FixedUpdate, (
+                // Runs after death as well, so a player hit in the air still lands
+                (update_jump, update_hitbox).chain().in_set(GameSystems::Movement).run_if(not(in_state(GameState::Paused))),
+                check_for_collisions.in_set(GameSystems::Collision).run_if(world_running),
+            ))
+           .add_systems(OnEnter(GameState::Reset), reset_player)
            .add_observer(handle_input)
-           .add_observer(handle_player_reset);
+           .add_observer(handle_release)
+           .add_observer(handle_duck)
+           .add_observer(handle_stand_up);
     }
 }
 
+// Played once when the player dies, the death screen waits for it
+pub const DEAD_CLIP: &str = "dead";
+
 #[derive(Component, Debug, Clone, Copy, PartialEq)]
 enum PlayerState {
     Running,
     Jumping,
+    Ducking,
     Dead,
 }
 
-#[derive(Component)]
-struct AnimationConfig {
-    first_sprite_index: usize,
-    last_sprite_index: usize,
-    fps: u8,
-    frame_timer: Timer,
+impl PlayerState {
+    fn on_ground(&self) -> bool {
+        matches!(self, PlayerState::Running | PlayerState::Ducking)
+    }
 }
 
-impl AnimationConfig {
-    fn new(first: usize, last: usize, fps: u8) -> Self {
-        Self {
-            first_sprite_index: first,
-            last_sprite_index: last,
-            fps,
-            frame_timer: Self::timer_from_fps(fps),
+impl ClipState for PlayerState {
+    fn clip(&self) -> &'static str {
+        match self {
+            PlayerState::Running => "running",
+            // Switches to falling in update_jump
+            PlayerState::Jumping => "jumping",
+            PlayerState::Ducking => "ducking",
+            PlayerState::Dead => DEAD_CLIP,
         }
     }
-
-    fn timer_from_fps(fps: u8) -> Timer {
-        Timer::new(Duration::from_secs_f32(1.0 / (fps as f32)), TimerMode::Once)
-    }
 }
 
 #[derive(Component)]
-struct PlayerSprite;
+pub struct PlayerSprite;
 
 #[derive(Component)]
-struct Jump {
-    velocity: f32,
-    gravity: f32,
-    ground_y: f32,
+pub struct Jump {
+    pub velocity: f32,
+    pub gravity: f32,
+    pub ground_y: f32,
+    // Whether there is ground below the running player
+    pub supported: bool,
+    // Seconds left in which a second jump in the air is allowed
+    pub double_jump: f32,
+    double_jumped: bool,
+    // Whether the jump button is still held, letting go early makes the jump lower
+    held: bool,
+    // Seconds a press is kept to jump again on landing
+    buffered: f32,
+    // Seconds left to jump after losing the ground
+    coyote: f32,
 }
 
-#[derive(Component)]
-struct PlayerSpritesheets {
-    running_texture: Handle<Image>,
-    running_layout: Handle<TextureAtlasLayout>,
-    jumping_texture: Handle<Image>,
-    jumping_layout: Handle<TextureAtlasLayout>,
-    dead_texture: Handle<Image>,
-    dead_layout: Handle<TextureAtlasLayout>,
+impl Jump {
+    fn can_take_off(&self, state: PlayerState) -> bool {
+        state.on_ground() && (self.supported || self.coyote > 0.0)
+    }
+}
+
+// Whether the duck button is held, a player landing with it held ducks right away
+#[derive(Component, Default)]
+struct DuckHeld(bool);
+
+// The number of frames follows from the width of each sheet
+fn player_clips(mut loader: Option<SpritesheetLoader>) -> SpriteClips {
+    let mut sheet = |path| loader.as_mut().map(|loader| loader.load(path, SPRITE_FRAME_SIZE)).unwrap_or_default();
+    let jumping = sheet("textures/player/jumping.png");
+
+    SpriteClips::default()
+        .with("running", SpriteClip::new(sheet("textures/player/running.png"), 4., Playback::Loop))
+        // Jumping has a frame going up and one going down
+        .with("jumping", SpriteClip::new(jumping.clone(), 1., Playback::Once).frames(0, 0))
+        .with("falling", SpriteClip::new(jumping, 1., Playback::Once).frames(1, 1))
+        .with("ducking", SpriteClip::new(sheet("textures/player/ducking.png"), 4., Playback::Loop))
+        .with(DEAD_CLIP, SpriteClip::new(sheet("textures/player/dead.png"), 4., Playback::Once))
 }
 
 fn setup_player(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    loader: Option<SpritesheetLoader>,
 ) {
-    // Load all spritesheets
-    let running_texture = asset_server.load("textures/player/running.png");
-    let jumping_texture = asset_server.load("textures/player/jumping.png");
-    let dead_texture = asset_server.load("textures/player/dead.png");
-
-    // Create layouts for each spritesheet
-    // Running: 2 sprites in 1 row, 2 columns (240x240 each)
-    let running_layout = TextureAtlasLayout::from_grid(UVec2::splat(240), 2, 1, None, None);
-    let running_layout_handle = texture_atlas_layouts.add(running_layout);
-
-    // Jumping: 2 sprites in 1 row, 2 columns (240x240 each) - up and down
-    let jumping_layout = TextureAtlasLayout::from_grid(UVec2::splat(240), 2, 1, None, None);
-    let jumping_layout_handle = texture_atlas_layouts.add(jumping_layout);
-
-    // Dead: 1 sprite (240x240)
-    let dead_layout = TextureAtlasLayout::from_grid(UVec2::splat(240), 1, 1, None, None);
-    let dead_layout_handle = texture_atlas_layouts.add(dead_layout);
-
     // Calculate player position
     let player_x = PLAYER_X;
     let player_y = PLAYER_GROUND;
 
-    // Running animation config (4 FPS, 2 frames)
-    let animation_config = AnimationConfig::new(0, 1, 4);
-
-    // Create the player sprite with running state
+    // Create the player sprite with running state, the animation sets its texture
     commands.spawn((
-        Sprite {
-            image: running_texture.clone(),
-            texture_atlas: Some(TextureAtlas {
-                layout: running_layout_handle.clone(),
-                index: 0,
-            }),
-            ..default()
-        },
+        Sprite::default(),
         Transform::from_xyz(player_x, player_y, 0.0).with_scale(Vec3::splat(TILE_SCALE)),
         PlayerSprite,
         PlayerState::Running,
-        animation_config,
-        PlayerSpritesheets {
-            running_texture,
-            running_layout: running_layout_handle,
-            jumping_texture,
-            jumping_layout: jumping_layout_handle,
-            dead_texture,
-            dead_layout: dead_layout_handle,
-        },
+        Hitbox::new(&PLAYER_RUNNING_HITBOX),
+        player_clips(loader),
+        SpriteAnimation::new(PlayerState::Running.clip()),
         Jump {
             velocity: 0.0,
-            gravity: -980.0,
+            gravity: JUMP_GRAVITY,
             ground_y: player_y,
+            supported: true,
+            double_jump: 0.0,
+            double_jumped: false,
+            held: false,
+            buffered: 0.0,
+            coyote: JUMP_COYOTE_SECS,
         },
+        DuckHeld::default(),
     ));
 }
 
 
 fn handle_input(
     _jump: On<PlayerJump>,
-    mut query: Query<(&mut PlayerState, &Jump), With<PlayerSprite>>,
+    mut query: Query<(&mut PlayerState, &mut Jump), With<PlayerSprite>>,
 ) {
-    for (mut state, jump) in &mut query {
-        // Only allow jumping if on ground and in running state
-        if *state == PlayerState::Running && jump.velocity == 0.0 {
+    for (mut state, mut jump) in &mut query {
+        // Only a press after letting go is a new one
+        let fresh = !jump.held;
+        jump.held = true;
+
+        if jump.can_take_off(*state) {
             *state = PlayerState::Jumping;
+            jump.velocity = 0.0;
+        } else if *state == PlayerState::Jumping && fresh && jump.double_jump > 0.0 && !jump.double_jumped && jump.velocity != 0.0 {
+            // The power-up allows one more jump in the air
+            jump.double_jumped = true;
+            jump.velocity = JUMP_VELOCITY;
+        } else if *state == PlayerState::Jumping {
+            // Too early, jump as soon as the player landed
+            jump.buffered = JUMP_BUFFER_SECS;
+        }
+    }
+}
+
+
+fn handle_release(
+    _release: On<PlayerJumpReleased>,
+    mut query: Query<&mut Jump, With<PlayerSprite>>,
+) {
+    for mut jump in &mut query {
+        jump.held = false;
+        jump.velocity = jump.velocity.min(JUMP_RELEASE_VELOCITY);
+    }
+}
+
+
+fn handle_duck(
+    _duck: On<PlayerDuck>,
+    mut query: Query<(&mut PlayerState, &mut DuckHeld), With<PlayerSprite>>,
+) {
+    for (mut state, mut duck) in &mut query {
+        duck.0 = true;
+
+        if *state == PlayerState::Running {
+            *state = PlayerState::Ducking;
+        }
+    }
+}
+
+
+fn handle_stand_up(
+    _stand_up: On<PlayerStandUp>,
+    mut query: Query<(&mut PlayerState, &mut DuckHeld), With<PlayerSprite>>,
+) {
+    for (mut state, mut duck) in &mut query {
+        duck.0 = false;
+
+        if *state == PlayerState::Ducking {
+            *state = PlayerState::Running;
         }
     }
 }
 
 
 fn update_jump(
+    mut commands: Commands,
     time: Res<Time>,
     game: Res<GameData>,
-    mut query: Query<(&mut Transform, &mut Jump, &mut PlayerState, &mut Sprite), With<PlayerSprite>>,
+    mut query: Query<(&mut Transform, &mut Jump, &mut PlayerState, &mut SpriteAnimation, &DuckHeld), With<PlayerSprite>>,
 ) {
-    for (mut transform, mut jump, mut state, mut sprite) in &mut query {
+    for (mut transform, mut jump, mut state, mut animation, duck) in &mut query {
+        jump.buffered = (jump.buffered - time.delta_secs()).max(0.0);
+        jump.double_jump = (jump.double_jump - time.delta_secs()).max(0.0);
+
+        if state.on_ground() {
+            if jump.supported {
+                jump.coyote = JUMP_COYOTE_SECS;
+            } else {
+                jump.coyote = (jump.coyote - time.delta_secs()).max(0.0);
+
+                // Ran into the water without jumping in time
+                if jump.coyote <= 0.0 && game.killed_by.is_none() {
+                    *state = PlayerState::Dead;
+                    transform.translation.y = jump.ground_y - WATER_SINK_DEPTH;
+                    commands.trigger(GameEnd { killed_by: KilledBy::Water });
+                }
+            }
+        }
 
         if *state == PlayerState::Jumping {
-            // Apply jump velocity on state change
+            // Apply jump velocity on state change, a released button only hops
             if jump.velocity == 0.0 {
-                jump.velocity = 500.0; // Initial jump velocity
+                jump.velocity = if jump.held { JUMP_VELOCITY } else { JUMP_RELEASE_VELOCITY };
             }
 
             // Apply gravity
             jump.velocity += jump.gravity * time.delta_secs();
             transform.translation.y += jump.velocity * time.delta_secs();
 
-            // Update sprite index based on velocity (0 = up, 1 = down)
-            if let Some(atlas) = &mut sprite.texture_atlas {
-                if jump.velocity > 0.0 {
-                    atlas.index = 0; // Going up
-                } else {
-                    atlas.index = 1; // Going down
-                }
-            }
+            // Show going up or down based on velocity
+            animation.play(if jump.velocity > 0.0 { "jumping" } else { "falling" });
 
             // Check if landed
             if transform.translation.y <= jump.ground_y {
                 transform.translation.y = jump.ground_y;
                 jump.velocity = 0.0;
-                match game.game_state {
-                    GameState::Dead => {
-                        *state = PlayerState::Dead;
-                    }
-                    _ => {
-                        *state = PlayerState::Running;
-                    }
+                jump.double_jumped = false;
+                // The player was hit during the jump
+                if game.killed_by.is_some() {
+                    *state = PlayerState::Dead;
+                } else if jump.buffered > 0.0 {
+                    // Stays jumping, the next jump takes off in the next tick
+                    jump.buffered = 0.0;
+                } else if duck.0 {
+                    *state = PlayerState::Ducking;
+                } else {
+                    *state = PlayerState::Running;
                 }
             }
         }
@@ -187,101 +258,64 @@ fn update_jump(
 }
 
 
-fn execute_animations(
-    time: Res<Time>,
-    mut query: Query<(
-        &mut AnimationConfig,
-        &mut Sprite,
-        &PlayerState,
-        &PlayerSpritesheets,
-    )>,
+fn update_hitbox(
+    mut query: Query<(&PlayerState, &mut Hitbox), Changed<PlayerState>>,
 ) {
-    for (mut config, mut sprite, state, spritesheets) in &mut query {
-        match state {
-            PlayerState::Running => {
-                
-                if sprite.image != spritesheets.running_texture {
-                    sprite.image = spritesheets.running_texture.clone();
-                    if let Some(atlas) = &mut sprite.texture_atlas {
-                        atlas.layout = spritesheets.running_layout.clone();
-                        atlas.index = 0;
-                    }
-                    config.first_sprite_index = 0;
-                    config.last_sprite_index = 1;
-                    config.fps = 4;
-                }
-
-                // Animate running
-                config.frame_timer.tick(time.delta());
-                if config.frame_timer.just_finished() {
-                    if let Some(atlas) = &mut sprite.texture_atlas {
-                        if atlas.index >= config.last_sprite_index {
-                            atlas.index = config.first_sprite_index;
-                        } else {
-                            atlas.index += 1;
-                        }
-                    }
-                    config.frame_timer = AnimationConfig::timer_from_fps(config.fps);
-                }
-            }
-            PlayerState::Jumping => {
-                if sprite.image != spritesheets.jumping_texture {
-                    sprite.image = spritesheets.jumping_texture.clone();
-                    if let Some(atlas) = &mut sprite.texture_atlas {
-                        atlas.layout = spritesheets.jumping_layout.clone();
-                        atlas.index = 0;
-                    }
-                }
-                // Jump animation is handled in update_jump based on velocity
-            }
-            PlayerState::Dead => {
-                if sprite.image != spritesheets.dead_texture {
-                    sprite.image = spritesheets.dead_texture.clone();
-                    if let Some(atlas) = &mut sprite.texture_atlas {
-                        atlas.layout = spritesheets.dead_layout.clone();
-                        atlas.index = 0;
-                    }
-                }
-                // Dead state has no animation
-            }
-        }
+    for (state, mut hitbox) in &mut query {
+        *hitbox = match state {
+            PlayerState::Jumping => Hitbox::new(&PLAYER_JUMPING_HITBOX),
+            PlayerState::Ducking => Hitbox::new(&PLAYER_DUCKING_HITBOX),
+            _ => Hitbox::new(&PLAYER_RUNNING_HITBOX),
+        };
     }
 }
 
 
 fn check_for_collisions(
     mut commands: Commands,
-    mut player_query: Query<(&Transform, &mut PlayerState), With<PlayerSprite>>,
-    enemy_query: Query<(&Transform), With<EnemySprite>>,
+    mut player_query: Query<(&Transform, &Hitbox, &mut PlayerState), With<PlayerSprite>>,
+    enemy_query: Query<(&Transform, &Hitbox, &EnemyKind), With<EnemySprite>>,
+    roster: Res<EnemyRoster>,
 ) {
-    for enemy_transform in & enemy_query {
+    for (enemy_transform, enemy_hitbox, kind) in & enemy_query {
+
+        for (player_transform, player_hitbox, mut player_state) in & mut player_query {
 
-        for (player_transform, mut player_state) in & mut player_query {
+            let player_position = player_transform.translation.truncate();
+            let enemy_position = enemy_transform.translation.truncate();
 
-            let distance = player_transform.translation.distance(enemy_transform.translation);
-            
-            if distance < COLLISION_RADIUS
+            if player_hitbox.overlaps(player_position, enemy_hitbox, enemy_position)
             {
                 // if the player is jumping, let it land first
-                if *player_state == PlayerState::Running {
+                if player_state.on_ground() {
 
                     *player_state = PlayerState::Dead;
                 }
 
-                commands.trigger(GameEnd);
+                commands.trigger(GameEnd { killed_by: KilledBy::Enemy(roster.definition(*kind).name.clone()) });
             }
         }
     }
 }
 
-fn handle_player_reset(
-    _evt: On<GameReset>,
-    mut player_query: Query<(&mut PlayerState), With<PlayerSprite>>
+fn reset_player(
+    mut player_query: Query<(&mut Transform, &mut PlayerState, &mut Jump, &mut DuckHeld), With<PlayerSprite>>
 )
 {
-    for mut player_state in &mut player_query {
+    for (mut transform, mut player_state, mut jump, mut duck) in &mut player_query {
 
         *player_state = PlayerState::Running;
+        // A drowned player sank below the ground
+        transform.translation.y = jump.ground_y;
+        duck.0 = false;
+        // A player killed in the air may still be falling
+        jump.velocity = 0.0;
+        jump.held = false;
+        jump.supported = true;
+        jump.double_jump = 0.0;
+        jump.double_jumped = false;
+        jump.buffered = 0.0;
+        jump.coyote = JUMP_COYOTE_SECS;
     }
 
 }
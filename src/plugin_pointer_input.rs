@@ -0,0 +1,67 @@
+//! Pointer input plugin module
+//!
+//! A click anywhere triggers the bound actions. Touches trigger the actions of their gesture,
+//! which depends on the half of the window they start on and on the number of fingers.
+
+use bevy::input::touch::Touch;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::plugin_actions::{ActionSystems, Actions, Bindings, TouchGesture, queue_actions};
+
+
+pub struct PointerInputPlugin;
+
+impl Plugin for PointerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, handle_input.in_set(ActionSystems));
+    }
+}
+
+
+// Whether a single touch makes the gesture, two fingers are counted over all touches
+fn single_touch(gesture: TouchGesture, touch: &Touch, middle: f32) -> bool {
+    match gesture {
+        TouchGesture::Tap => true,
+        TouchGesture::UpperHalf => touch.start_position().y < middle,
+        TouchGesture::LowerHalf => touch.start_position().y >= middle,
+        TouchGesture::TwoFingers => false,
+    }
+}
+
+
+fn handle_input(
+    mut actions: ResMut<Actions>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    bindings: Res<Bindings>,
+    window: Query<&Window, With<PrimaryWindow>>,
+) {
+    queue_actions(
+        &mut actions,
+        &bindings.mouse,
+        |button| mouse.just_pressed(*button),
+        |button| mouse.just_released(*button),
+    );
+
+    // Window positions grow downwards, without a window every touch is on the upper half
+    let middle = window.single().map_or(f32::INFINITY, |window| window.height() / 2.);
+    // A canceled touch is let go of as well, so nothing stays held
+    let ended = || touches.iter_just_released().chain(touches.iter_just_canceled());
+    let held = touches.iter().count();
+    let pressed = touches.iter_just_pressed().count();
+    let released = ended().count();
+
+    queue_actions(
+        &mut actions,
+        &bindings.touch,
+        |gesture| match gesture {
+            TouchGesture::TwoFingers => held >= 2 && held - pressed < 2,
+            _ => touches.iter_just_pressed().any(|touch| single_touch(*gesture, touch, middle)),
+        },
+        |gesture| match gesture {
+            TouchGesture::TwoFingers => held < 2 && held + released >= 2,
+            _ => ended().any(|touch| single_touch(*gesture, touch, middle)),
+        },
+    );
+}
@@ -0,0 +1,125 @@
+//! Power-up plugin module
+//!
+//! Every few thousand metres a power-up floats by at running height. Collecting it allows a
+//! second jump in the air for a while.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::constants::*;
+use crate::hitbox::{Hitbox, POWERUP_HITBOX};
+use crate::model::{GameData, GameState, GameSystems, WorldRng, world_running};
+use crate::plugin_player::{Jump, PlayerSprite};
+
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NextPowerUp(0.))
+           .add_systems(Startup, schedule_first_powerup)
+           .add_systems(FixedUpdate, (
+                move_powerups.in_set(GameSystems::Movement).run_if(world_running),
+                collect_powerups.in_set(GameSystems::Collision).run_if(world_running),
+            ))
+           .add_systems(OnEnter(GameState::Reset), (reset_powerups, schedule_first_powerup));
+    }
+}
+
+
+#[derive(Component)]
+struct PowerUp;
+
+
+// Distance at which the next power-up shows up at the right edge
+#[derive(Resource)]
+struct NextPowerUp(f32);
+
+
+fn schedule_first_powerup(
+    mut rng: ResMut<WorldRng>,
+    mut next: ResMut<NextPowerUp>,
+)
+{
+    next.0 = rng.powerups.random_range(POWERUP_MINIMUM_SPACE..POWERUP_MAXIMUM_SPACE);
+}
+
+
+fn spawn_powerup(
+    commands: &mut Commands,
+    x: f32,
+)
+{
+    let label = commands.spawn((
+        Text2d::new("2x"),
+        TextFont {
+            font_size: POWERUP_SIZE / 2.,
+            ..default()
+        },
+        TextColor(Color::BLACK),
+        Transform::from_xyz(0.0, 0.0, 1.0),
+    )).id();
+
+    commands.spawn((
+        Sprite::from_color(POWERUP_COLOR, Vec2::splat(POWERUP_SIZE)),
+        Transform::from_xyz(x, POWERUP_Y, 0.),
+        PowerUp,
+        Hitbox::new(&POWERUP_HITBOX),
+    )).add_child(label);
+}
+
+
+fn move_powerups(
+    mut commands: Commands,
+    time: Res<Time>,
+    game: Res<GameData>,
+    mut rng: ResMut<WorldRng>,
+    mut next: ResMut<NextPowerUp>,
+    mut query: Query<(Entity, &mut Transform), With<PowerUp>>,
+)
+{
+    let move_distance = game.velocity * time.delta_secs();
+    let left_edge = -WINDOW_WIDTH / 2.0 - SCALED_TILE_SIZE;
+
+    for (entity, mut transform) in &mut query {
+        transform.translation.x -= move_distance;
+
+        if transform.translation.x < left_edge {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    if game.current_score >= next.0 {
+        spawn_powerup(&mut commands, WINDOW_WIDTH / 2.0 + SCALED_TILE_SIZE);
+        next.0 += rng.powerups.random_range(POWERUP_MINIMUM_SPACE..POWERUP_MAXIMUM_SPACE);
+    }
+}
+
+
+fn collect_powerups(
+    mut commands: Commands,
+    mut player_query: Query<(&Transform, &Hitbox, &mut Jump), With<PlayerSprite>>,
+    powerup_query: Query<(Entity, &Transform, &Hitbox), With<PowerUp>>,
+)
+{
+    for (player_transform, player_hitbox, mut jump) in &mut player_query {
+        let player_position = player_transform.translation.truncate();
+
+        for (entity, transform, hitbox) in &powerup_query {
+            if player_hitbox.overlaps(player_position, hitbox, transform.translation.truncate()) {
+                jump.double_jump = DOUBLE_JUMP_SECS;
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+
+fn reset_powerups(
+    mut commands: Commands,
+    query: Query<Entity, With<PowerUp>>,
+)
+{
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,256 @@
+//! Replay plugin module
+//!
+//! A replay is the seed of a run and the inputs of the player, timestamped with the fixed
+//! update tick of the state they happened in. The world only depends on the seed and the
+//! fixed ticks, so feeding the inputs back at the same ticks plays the same run again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::REPLAY_RESTART_SECS;
+use crate::model::{GameData, GameReset, GameStart, GameState, GameSystems, PlayerDuck, PlayerJump, PlayerJumpReleased, PlayerStandUp, StateTicks, WorldRng};
+use crate::plugin_highscores::RecordHighscores;
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ReplayInput {
+    Start,
+    Jump,
+    JumpRelease,
+    Duck,
+    StandUp,
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ReplayEvent {
+    pub tick: u64,
+    pub input: ReplayInput,
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub events: Vec<ReplayEvent>,
+    // Distance the run ended at, to check a replay still plays out the same
+    pub distance: f32,
+}
+
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ron::from_str(&content).map_err(|err| err.to_string())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::write(path, content).map_err(|err| err.to_string())
+    }
+}
+
+
+pub enum ReplayMode {
+    Record(PathBuf),
+    Play(Replay),
+}
+
+
+pub struct ReplayPlugin {
+    pub mode: ReplayMode,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match &self.mode {
+            ReplayMode::Record(path) => {
+                app.insert_resource(ReplayRecorder { path: path.clone(), replay: Replay::default() })
+                   .add_systems(OnEnter(GameState::WaitingForStart), start_recording)
+                   .add_systems(OnEnter(GameState::Dead), save_recording)
+                   .add_observer(record_start)
+                   .add_observer(record_jump)
+                   .add_observer(record_jump_release)
+                   .add_observer(record_duck)
+                   .add_observer(record_stand_up);
+            }
+            ReplayMode::Play(replay) => {
+                app.insert_resource(ReplayPlayer {
+                        replay: replay.clone(),
+                        next_event: 0,
+                        restart_timer: Timer::default(),
+                    })
+                   // The recorded run was counted already
+                   .insert_resource(RecordHighscores(false))
+                   .add_systems(OnEnter(GameState::WaitingForStart), rewind_replay)
+                   .add_systems(FixedUpdate, play_replay.in_set(GameSystems::Input))
+                   .add_systems(OnEnter(GameState::Dead), wait_for_restart)
+                   .add_systems(Update, restart_replay.run_if(in_state(GameState::Dead)));
+            }
+        }
+    }
+}
+
+
+#[derive(Resource)]
+struct ReplayRecorder {
+    path: PathBuf,
+    replay: Replay,
+}
+
+
+#[derive(Resource)]
+struct ReplayPlayer {
+    replay: Replay,
+    next_event: usize,
+    restart_timer: Timer,
+}
+
+
+// The world is reseeded before waiting for the next start, so the seed is final here
+fn start_recording(
+    rng: Res<WorldRng>,
+    mut recorder: ResMut<ReplayRecorder>,
+)
+{
+    recorder.replay = Replay {
+        seed: rng.seed,
+        ..default()
+    };
+}
+
+
+fn record_start(
+    _evt: On<GameStart>,
+    ticks: Res<StateTicks>,
+    mut recorder: ResMut<ReplayRecorder>,
+)
+{
+    recorder.replay.events.push(ReplayEvent { tick: ticks.0, input: ReplayInput::Start });
+}
+
+
+fn record_jump(
+    _evt: On<PlayerJump>,
+    ticks: Res<StateTicks>,
+    mut recorder: ResMut<ReplayRecorder>,
+)
+{
+    recorder.replay.events.push(ReplayEvent { tick: ticks.0, input: ReplayInput::Jump });
+}
+
+
+fn record_jump_release(
+    _evt: On<PlayerJumpReleased>,
+    ticks: Res<StateTicks>,
+    mut recorder: ResMut<ReplayRecorder>,
+)
+{
+    recorder.replay.events.push(ReplayEvent { tick: ticks.0, input: ReplayInput::JumpRelease });
+}
+
+
+fn record_duck(
+    _evt: On<PlayerDuck>,
+    ticks: Res<StateTicks>,
+    mut recorder: ResMut<ReplayRecorder>,
+)
+{
+    recorder.replay.events.push(ReplayEvent { tick: ticks.0, input: ReplayInput::Duck });
+}
+
+
+fn record_stand_up(
+    _evt: On<PlayerStandUp>,
+    ticks: Res<StateTicks>,
+    mut recorder: ResMut<ReplayRecorder>,
+)
+{
+    recorder.replay.events.push(ReplayEvent { tick: ticks.0, input: ReplayInput::StandUp });
+}
+
+
+fn save_recording(
+    game: Res<GameData>,
+    mut recorder: ResMut<ReplayRecorder>,
+)
+{
+    recorder.replay.distance = game.current_score;
+
+    match recorder.replay.save(&recorder.path) {
+        Ok(()) => info!("Saved replay to {}", recorder.path.display()),
+        Err(err) => warn!("Could not save replay to {}: {}", recorder.path.display(), err),
+    }
+}
+
+
+fn rewind_replay(
+    mut player: ResMut<ReplayPlayer>
+)
+{
+    player.next_event = 0;
+}
+
+
+// Start events are timestamped while waiting, all others while running
+fn play_replay(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    ticks: Res<StateTicks>,
+    mut player: ResMut<ReplayPlayer>,
+)
+{
+    while let Some(event) = player.replay.events.get(player.next_event).copied() {
+        let due = match (event.input, state.get()) {
+            (ReplayInput::Start, GameState::WaitingForStart) => event.tick <= ticks.0,
+            (ReplayInput::Start, _) => false,
+            (_, GameState::Running) => event.tick <= ticks.0,
+            _ => false,
+        };
+
+        if !due {
+            return;
+        }
+
+        match event.input {
+            ReplayInput::Start => commands.trigger(GameStart),
+            ReplayInput::Jump => commands.trigger(PlayerJump),
+            ReplayInput::JumpRelease => commands.trigger(PlayerJumpReleased),
+            ReplayInput::Duck => commands.trigger(PlayerDuck),
+            ReplayInput::StandUp => commands.trigger(PlayerStandUp),
+        }
+        player.next_event += 1;
+    }
+}
+
+
+fn wait_for_restart(
+    game: Res<GameData>,
+    mut player: ResMut<ReplayPlayer>,
+)
+{
+    if game.current_score != player.replay.distance {
+        warn!("Replay ended at {} instead of {}", game.current_score, player.replay.distance);
+    }
+
+    player.restart_timer = Timer::new(Duration::from_secs_f32(REPLAY_RESTART_SECS), TimerMode::Once);
+}
+
+
+// Watching a replay loops it
+fn restart_replay(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut player: ResMut<ReplayPlayer>,
+)
+{
+    player.restart_timer.tick(time.delta());
+
+    if player.restart_timer.just_finished() {
+        commands.trigger(GameReset);
+    }
+}
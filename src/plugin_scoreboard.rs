@@ -3,16 +3,20 @@ use bevy::prelude::*;
 
 use crate::constants::*;
 use crate::model::GameData;
+use crate::plugin_player::{Jump, PlayerSprite};
 
 #[derive(Component)]
 struct ScoreboardUi;
 
+#[derive(Component)]
+struct DoubleJumpUi;
+
 
 pub struct Scoreboard;
 impl Plugin for Scoreboard {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_scoreboard)
-           .add_systems(Update, update_scoreboard);
+           .add_systems(Update, (update_scoreboard, update_double_jump));
     }
 }
 
@@ -43,6 +47,29 @@ fn setup_scoreboard(mut commands: Commands)
             TextColor(SCORE_COLOR),
         )],
     ));
+
+    setup_double_jump(&mut commands);
+}
+
+
+// Only shown while the double jump power-up lasts
+fn setup_double_jump(commands: &mut Commands)
+{
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: HUD_FONT_SIZE,
+            ..default()
+        },
+        TextColor(POWERUP_COLOR),
+        DoubleJumpUi,
+        Node {
+            position_type: PositionType::Absolute,
+            top: HUD_TOP,
+            left: SCOREBOARD_TEXT_PADDING,
+            ..default()
+        },
+    ));
 }
 
 
@@ -54,3 +81,19 @@ fn update_scoreboard(
     let current_score = game.current_score as usize;
     *writer.text(*score_root, 1) = current_score.to_string();
 }
+
+
+fn update_double_jump(
+    jump: Single<&Jump, With<PlayerSprite>>,
+    mut text: Single<&mut Text, With<DoubleJumpUi>>,
+) {
+    let line = if jump.double_jump > 0.0 {
+        format!("Double jump {}s", jump.double_jump.ceil() as usize)
+    } else {
+        String::new()
+    };
+
+    if text.0 != line {
+        text.0 = line;
+    }
+}
@@ -2,6 +2,7 @@ use bevy::prelude::*;
 
 use crate::constants::*;
 use crate::model::*;
+use crate::plugin_spritesheets::SpritesheetLoader;
 
 pub struct SignPlugin;
 
@@ -9,8 +10,8 @@ impl Plugin for SignPlugin {
     fn build(&self, app: &mut App)
     {
         app.add_systems(Startup, setup_sign)
-           .add_systems(FixedUpdate, move_sign)
-           .add_observer(handle_sign_reset);
+           .add_systems(FixedUpdate, move_sign.in_set(GameSystems::Movement).run_if(world_running))
+           .add_systems(OnEnter(GameState::Reset), reset_signs);
     }
 }
 
@@ -19,30 +20,32 @@ impl Plugin for SignPlugin {
 struct SignSprite;
 
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 struct SignTextures {
     sign: Handle<Image>,
     layout: Handle<TextureAtlasLayout>,
 }
 
+impl SignTextures {
+    fn load(loader: &mut SpritesheetLoader) -> Self {
+        let (sign, layout) = loader.load("textures/ground/distance_sign.png", SPRITE_FRAME_SIZE);
+
+        Self { sign, layout }
+    }
+}
+
 fn setup_sign(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    loader: Option<SpritesheetLoader>,
 ) {
+    let textures = match loader {
+        Some(mut loader) => SignTextures::load(&mut loader),
+        None => SignTextures::default(),
+    };
 
-    let sign_texture = asset_server.load("textures/ground/distance_sign.png");
-
-    // Create texture atlas layout (1 sprite, 240x240)
-    let layout = TextureAtlasLayout::from_grid(UVec2::splat(240), 1, 1, None, None);
-    let layout_handle = texture_atlas_layouts.add(layout);
-
-    commands.insert_resource(SignTextures {
-        sign: sign_texture.clone(),
-        layout: layout_handle.clone()
-    });
+    spawn_sign(&mut commands, PLAYER_X + 1000., &textures.sign, &textures.layout, 1000.);
 
-    spawn_sign(&mut commands, PLAYER_X + 1000., &sign_texture, &layout_handle, 1000.);
+    commands.insert_resource(textures);
 }
 
 
@@ -61,7 +64,7 @@ fn spawn_sign(
             image: texture,
             texture_atlas: Some(TextureAtlas { layout: layout.clone(), index: 0 }), ..default()
         },
-        Transform::from_xyz(x, PLAYER_GROUND, -1.0).with_scale(Vec3::splat((TILE_SCALE))),
+        Transform::from_xyz(x, PLAYER_GROUND, -1.0).with_scale(Vec3::splat(TILE_SCALE)),
         SignSprite
     )).id();
 
@@ -88,48 +91,41 @@ fn move_sign(
     textures: Res<SignTextures>,
     mut query: Query<(Entity, &mut Transform), With<SignSprite>>,
 ) {
-    match game.game_state {
-        GameState::Running => {
-            let move_distance = game.velocity * time.delta_secs();
-            let left_edge = -WINDOW_WIDTH / 2.0 - SCALED_TILE_SIZE;
-            let right_edge = WINDOW_WIDTH / 2.0;
-            
-            let mut rightmost_x = f32::MIN;
-
-            for (entity, mut transform) in &mut query {
-                // Move tile to the left
-                transform.translation.x -= move_distance;
-
-                // Track the rightmost tile position
-                if transform.translation.x > rightmost_x {
-                    rightmost_x = transform.translation.x;
-                }
-
-                // If tile has moved off the left edge, despawn it
-                if transform.translation.x < left_edge {
-                    commands.entity(entity).despawn();
-                }
-            }
-
-            if rightmost_x < right_edge - SCALED_TILE_SIZE / 2. {
-                
-                let new_x = rightmost_x + 1000.;
-                let distance = new_x - PLAYER_X + game.current_score;
-                spawn_sign(&mut commands, new_x, &textures.sign, &textures.layout, distance);
-            }
+    let move_distance = game.velocity * time.delta_secs();
+    let left_edge = -WINDOW_WIDTH / 2.0 - SCALED_TILE_SIZE;
+    let right_edge = WINDOW_WIDTH / 2.0;
+    
+    let mut rightmost_x = f32::MIN;
+
+    for (entity, mut transform) in &mut query {
+        // Move tile to the left
+        transform.translation.x -= move_distance;
+
+        // Track the rightmost tile position
+        if transform.translation.x > rightmost_x {
+            rightmost_x = transform.translation.x;
+        }
 
+        // If tile has moved off the left edge, despawn it
+        if transform.translation.x < left_edge {
+            commands.entity(entity).despawn();
         }
-        _ => {}
     }
+
+    if rightmost_x < right_edge - SCALED_TILE_SIZE / 2. {
+        
+        let new_x = rightmost_x + 1000.;
+        let distance = new_x - PLAYER_X + game.current_score;
+        spawn_sign(&mut commands, new_x, &textures.sign, &textures.layout, distance);
+    }
+
 }
 
 
-fn handle_sign_reset(
-    _evt: On<GameReset>,
+fn reset_signs(
     mut commands: Commands,
-    game: Res<GameData>,
     textures: Res<SignTextures>,
-    sign_query: Query<(Entity), With<SignSprite>>,
+    sign_query: Query<Entity, With<SignSprite>>,
 )
 {
     for enemy_entity in & sign_query {
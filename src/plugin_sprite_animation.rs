@@ -0,0 +1,309 @@
+//! Sprite animation plugin module
+//!
+//! An animated entity has a set of named clips and plays one of them at a time. A clip is a
+//! range of frames in a spritesheet, switching to a clip of another sheet swaps the texture.
+//! Components like the player state pick the clip through `ClipState`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::model::GameState;
+use crate::plugin_spritesheets::frame_count;
+
+pub struct SpriteAnimationPlugin;
+
+impl Plugin for SpriteAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, animate_sprites.in_set(SpriteAnimationSystems).run_if(not(in_state(GameState::Paused))));
+    }
+}
+
+// Systems choosing a clip run before this, so it is shown in the same frame
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpriteAnimationSystems;
+
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum Playback {
+    #[default]
+    Loop,
+    // Stops on the last frame
+    Once,
+    // Runs back and forth
+    PingPong,
+}
+
+
+#[derive(Clone, Debug)]
+pub struct SpriteClip {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub first: usize,
+    // Runs to the end of the sheet when not given
+    pub last: Option<usize>,
+    pub fps: f32,
+    pub playback: Playback,
+}
+
+
+impl SpriteClip {
+    // All frames of a sheet
+    pub fn new(sheet: (Handle<Image>, Handle<TextureAtlasLayout>), fps: f32, playback: Playback) -> Self {
+        let (image, layout) = sheet;
+
+        Self { image, layout, first: 0, last: None, fps, playback }
+    }
+
+    pub fn frames(mut self, first: usize, last: usize) -> Self {
+        self.first = first;
+        self.last = Some(last);
+        self
+    }
+}
+
+
+// The clips of an entity by name
+#[derive(Component, Clone, Debug, Default)]
+pub struct SpriteClips(HashMap<&'static str, SpriteClip>);
+
+
+impl SpriteClips {
+    pub fn with(mut self, name: &'static str, clip: SpriteClip) -> Self {
+        self.0.insert(name, clip);
+        self
+    }
+}
+
+
+// The clip being played, it is shown from its first frame once the animation runs
+#[derive(Component, Debug)]
+pub struct SpriteAnimation {
+    clip: &'static str,
+    restart: bool,
+    finished: bool,
+    backwards: bool,
+    frame_timer: Timer,
+}
+
+
+impl SpriteAnimation {
+    pub fn new(clip: &'static str) -> Self {
+        Self {
+            clip,
+            restart: true,
+            finished: false,
+            backwards: false,
+            frame_timer: Timer::default(),
+        }
+    }
+
+    // Playing the clip already running keeps it going
+    pub fn play(&mut self, clip: &'static str) {
+        if self.clip != clip {
+            self.clip = clip;
+            self.restart = true;
+        }
+    }
+}
+
+
+// Triggered when a clip played once has shown its last frame
+#[derive(EntityEvent)]
+pub struct ClipFinished {
+    pub entity: Entity,
+    pub clip: &'static str,
+}
+
+
+// A component which decides the clip of its entity
+pub trait ClipState: Component {
+    fn clip(&self) -> &'static str;
+}
+
+
+// Plays the clip of the state whenever it changes
+pub fn play_state_clips<S: ClipState>(
+    mut query: Query<(&S, &mut SpriteAnimation), Changed<S>>,
+) {
+    for (state, mut animation) in &mut query {
+        animation.play(state.clip());
+    }
+}
+
+
+fn animate_sprites(
+    mut commands: Commands,
+    time: Res<Time>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut query: Query<(Entity, &SpriteClips, &mut SpriteAnimation, &mut Sprite)>,
+) {
+    for (entity, clips, mut animation, mut sprite) in &mut query {
+        let Some(clip) = clips.0.get(animation.clip) else {
+            warn_once!("Sprite clip {} is missing", animation.clip);
+            continue;
+        };
+
+        if animation.restart {
+            sprite.image = clip.image.clone();
+            sprite.texture_atlas = Some(TextureAtlas { layout: clip.layout.clone(), index: clip.first });
+            animation.restart = false;
+            animation.finished = false;
+            animation.backwards = false;
+            animation.frame_timer = Timer::new(Duration::from_secs_f32(1.0 / clip.fps), TimerMode::Repeating);
+            continue;
+        }
+
+        animation.frame_timer.tick(time.delta());
+        if animation.finished || !animation.frame_timer.just_finished() {
+            continue;
+        }
+
+        let Some(atlas) = &mut sprite.texture_atlas else {
+            continue;
+        };
+        let last = clip.last.unwrap_or_else(|| frame_count(&layouts, atlas) - 1).max(clip.first);
+
+        match clip.playback {
+            Playback::Loop => {
+                atlas.index = if atlas.index >= last { clip.first } else { atlas.index + 1 };
+            }
+            Playback::Once => {
+                if atlas.index >= last {
+                    animation.finished = true;
+                    commands.trigger(ClipFinished { entity, clip: animation.clip });
+                } else {
+                    atlas.index += 1;
+                }
+            }
+            Playback::PingPong => {
+                if atlas.index >= last {
+                    animation.backwards = true;
+                } else if atlas.index <= clip.first {
+                    animation.backwards = false;
+                }
+
+                atlas.index = if animation.backwards {
+                    atlas.index.saturating_sub(1).max(clip.first)
+                } else {
+                    (atlas.index + 1).min(last)
+                };
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::AssetPlugin;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    // Every update of the app shows the next frame of a clip at this rate
+    const FPS: f32 = 10.;
+
+    fn animation_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()))
+           .init_asset::<TextureAtlasLayout>()
+           .init_state::<GameState>()
+           .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(1.0 / FPS)))
+           .add_plugins(SpriteAnimationPlugin);
+        app
+    }
+
+    // A sheet of four frames in a row
+    fn sheet(app: &mut App) -> (Handle<Image>, Handle<TextureAtlasLayout>) {
+        let layout = TextureAtlasLayout::from_grid(UVec2::ONE, 4, 1, None, None);
+        (Handle::default(), app.world_mut().resource_mut::<Assets<TextureAtlasLayout>>().add(layout))
+    }
+
+    fn spawn(app: &mut App, clips: SpriteClips, clip: &'static str) -> Entity {
+        app.world_mut().spawn((Sprite::default(), clips, SpriteAnimation::new(clip))).id()
+    }
+
+    // The frame shown after each of the given number of updates
+    fn frames(app: &mut App, entity: Entity, count: usize) -> Vec<usize> {
+        (0..count).map(|_| {
+            app.update();
+            app.world().get::<Sprite>(entity).unwrap().texture_atlas.as_ref().unwrap().index
+        }).collect()
+    }
+
+    #[test]
+    fn loop_starts_over_after_its_last_frame() {
+        let mut app = animation_app();
+        let sheet = sheet(&mut app);
+        let entity = spawn(&mut app, SpriteClips::default().with("walk", SpriteClip::new(sheet, FPS, Playback::Loop).frames(1, 3)), "walk");
+
+        assert_eq!(frames(&mut app, entity, 6), vec![1, 2, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame_of_the_sheet() {
+        let mut app = animation_app();
+        let sheet = sheet(&mut app);
+        let entity = spawn(&mut app, SpriteClips::default().with("die", SpriteClip::new(sheet, FPS, Playback::Once)), "die");
+
+        assert_eq!(frames(&mut app, entity, 6), vec![0, 1, 2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn once_reports_its_end_a_single_time() {
+        let mut app = animation_app();
+        let sheet = sheet(&mut app);
+        let entity = spawn(&mut app, SpriteClips::default().with("die", SpriteClip::new(sheet, FPS, Playback::Once)), "die");
+
+        #[derive(Resource, Default)]
+        struct Finished(Vec<(Entity, &'static str)>);
+        app.init_resource::<Finished>()
+           .add_observer(|evt: On<ClipFinished>, mut finished: ResMut<Finished>| finished.0.push((evt.entity, evt.clip)));
+
+        frames(&mut app, entity, 4);
+        assert!(app.world().resource::<Finished>().0.is_empty(), "the last frame is still shown");
+
+        frames(&mut app, entity, 8);
+        assert_eq!(app.world().resource::<Finished>().0, vec![(entity, "die")]);
+    }
+
+    #[test]
+    fn ping_pong_runs_back_and_forth() {
+        let mut app = animation_app();
+        let sheet = sheet(&mut app);
+        let entity = spawn(&mut app, SpriteClips::default().with("fly", SpriteClip::new(sheet, FPS, Playback::PingPong).frames(0, 2)), "fly");
+
+        assert_eq!(frames(&mut app, entity, 7), vec![0, 1, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn another_clip_starts_from_its_first_frame() {
+        let mut app = animation_app();
+        let sheet = sheet(&mut app);
+        let clips = SpriteClips::default()
+            .with("running", SpriteClip::new(sheet.clone(), FPS, Playback::Loop).frames(0, 1))
+            .with("falling", SpriteClip::new(sheet, FPS, Playback::Once).frames(2, 3));
+        let entity = spawn(&mut app, clips, "running");
+        assert_eq!(frames(&mut app, entity, 2), vec![0, 1]);
+
+        app.world_mut().get_mut::<SpriteAnimation>(entity).unwrap().play("falling");
+        assert_eq!(frames(&mut app, entity, 3), vec![2, 3, 3]);
+
+        // Playing the clip again keeps it where it is
+        app.world_mut().get_mut::<SpriteAnimation>(entity).unwrap().play("falling");
+        assert_eq!(frames(&mut app, entity, 1), vec![3]);
+    }
+
+    #[test]
+    fn paused_game_holds_the_frame() {
+        let mut app = animation_app();
+        let sheet = sheet(&mut app);
+        let entity = spawn(&mut app, SpriteClips::default().with("walk", SpriteClip::new(sheet, FPS, Playback::Loop)), "walk");
+        assert_eq!(frames(&mut app, entity, 2), vec![0, 1]);
+
+        app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Paused);
+        assert_eq!(frames(&mut app, entity, 3), vec![1, 1, 1]);
+    }
+}
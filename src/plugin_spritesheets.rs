@@ -0,0 +1,91 @@
+//! Spritesheets plugin module
+//!
+//! Spritesheets are a grid of equally sized frames. Their layout starts with the first frame
+//! only and is filled in once the image is loaded, the columns and rows follow from the image
+//! size. A sheet gets more frames by making the image wider, without code changes.
+
+use bevy::asset::AssetPath;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+pub struct SpritesheetPlugin;
+
+impl Plugin for SpritesheetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingSpritesheets>()
+           .add_systems(Update, build_layouts);
+    }
+}
+
+
+struct PendingSpritesheet {
+    image: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+    frame_size: UVec2,
+}
+
+
+// Sheets whose image is still loading
+#[derive(Resource, Default)]
+struct PendingSpritesheets(Vec<PendingSpritesheet>);
+
+
+// Missing in headless runs, they leave out the spritesheet plugin and the texture atlas
+// assets, so their sprites have no textures
+#[derive(SystemParam)]
+pub struct SpritesheetLoader<'w> {
+    asset_server: Res<'w, AssetServer>,
+    layouts: ResMut<'w, Assets<TextureAtlasLayout>>,
+    pending: ResMut<'w, PendingSpritesheets>,
+}
+
+
+impl SpritesheetLoader<'_> {
+    pub fn load<'a>(
+        &mut self,
+        path: impl Into<AssetPath<'a>>,
+        frame_size: UVec2,
+    ) -> (Handle<Image>, Handle<TextureAtlasLayout>) {
+        let image = self.asset_server.load(path);
+        let layout = self.layouts.add(TextureAtlasLayout::from_grid(frame_size, 1, 1, None, None));
+
+        self.pending.0.push(PendingSpritesheet {
+            image: image.clone(),
+            layout: layout.clone(),
+            frame_size,
+        });
+
+        (image, layout)
+    }
+}
+
+
+// Number of frames in the sheet of an atlas, one as long as the image is loading
+pub fn frame_count(layouts: &Assets<TextureAtlasLayout>, atlas: &TextureAtlas) -> usize {
+    layouts.get(&atlas.layout).map_or(1, |layout| layout.len().max(1))
+}
+
+
+fn build_layouts(
+    images: Res<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut pending: ResMut<PendingSpritesheets>,
+) {
+    pending.0.retain(|sheet| {
+        let Some(image) = images.get(&sheet.image) else {
+            return true;
+        };
+
+        let grid = image.size() / sheet.frame_size;
+        if grid.min_element() == 0 {
+            warn!("Spritesheet {:?} is smaller than a frame of {}", sheet.image.path(), sheet.frame_size);
+            return false;
+        }
+
+        if let Some(layout) = layouts.get_mut(&sheet.layout) {
+            *layout = TextureAtlasLayout::from_grid(sheet.frame_size, grid.x, grid.y, None, None);
+        }
+
+        false
+    });
+}
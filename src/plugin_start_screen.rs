@@ -0,0 +1,103 @@
+//! Start screen plugin module
+
+use bevy::prelude::*;
+
+use crate::constants::*;
+use crate::model::{GameData, GameState, WorldRng};
+use crate::plugin_actions::{Action, Bindings};
+use crate::plugin_highscores::{Highscores, spawn_highscore_table};
+
+#[derive(Component)]
+struct StartScreenUi;
+
+
+pub struct StartScreenPlugin;
+
+impl Plugin for StartScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::WaitingForStart), setup_start_screen)
+           .add_systems(OnExit(GameState::WaitingForStart), despawn_start_screen);
+    }
+}
+
+
+fn setup_start_screen(
+    mut commands: Commands,
+    game: Res<GameData>,
+    rng: Res<WorldRng>,
+    highscores: Res<Highscores>,
+    bindings: Option<Res<Bindings>>,
+)
+{
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: SCREEN_ROW_GAP,
+            ..default()
+        },
+        StartScreenUi,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("Capybara Run!"),
+            TextFont {
+                font_size: SCREEN_TITLE_FONT_SIZE,
+                ..default()
+            },
+            TextColor(SCREEN_TITLE_COLOR),
+        ));
+
+        // The autopilot and replays start on their own
+        if let Some(buttons) = bindings.and_then(|bindings| bindings.describe(Action::Start)) {
+            parent.spawn((
+                Text::new(format!("Press {} to start", buttons)),
+                TextFont {
+                    font_size: SCREEN_TEXT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(SCREEN_TEXT_COLOR),
+            ));
+        }
+
+        // Only show the previous score once there was a run
+        if game.previous_score > 0. {
+            parent.spawn((
+                Text::new(format!("Previous score: {}", game.previous_score as usize)),
+                TextFont {
+                    font_size: SCREEN_TEXT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(SCREEN_TEXT_COLOR),
+            ));
+        }
+
+        if let Some(date) = &rng.daily_date {
+            let best = highscores.daily_best(date).map_or(0, |distance| distance as usize);
+
+            parent.spawn((
+                Text::new(format!("Daily run {} - best: {}", date, best)),
+                TextFont {
+                    font_size: SCREEN_TEXT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(SCREEN_TITLE_COLOR),
+            ));
+        }
+
+        spawn_highscore_table(parent, &highscores);
+    });
+}
+
+
+fn despawn_start_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<StartScreenUi>>,
+)
+{
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
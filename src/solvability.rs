@@ -0,0 +1,327 @@
+//! Checks whether a sequence of enemies and water can be cleared with the player's jump
+
+use bevy::math::Vec2;
+
+use crate::constants::*;
+use crate::enemy_definitions::EnemyDefinition;
+use crate::hitbox::{Hitbox, PLAYER_DUCKING_HITBOX, PLAYER_JUMPING_HITBOX, PLAYER_RUNNING_HITBOX};
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct JumpArc {
+    pub initial_velocity: f32,
+    pub gravity: f32,
+}
+
+
+impl Default for JumpArc {
+    fn default() -> Self {
+        Self {
+            initial_velocity: JUMP_VELOCITY,
+            gravity: JUMP_GRAVITY,
+        }
+    }
+}
+
+
+impl JumpArc {
+    pub fn airtime(&self) -> f32 {
+        2.0 * self.initial_velocity / -self.gravity
+    }
+
+    pub fn jump_length(&self, velocity: f32) -> f32 {
+        self.airtime() * velocity
+    }
+
+    // Height of the player for every tick in the air, integrated the same way as update_jump
+    fn heights(&self, timestep: f32) -> Vec<f32> {
+        assert!(self.gravity < 0.0, "a jump without gravity never lands");
+
+        let mut heights = Vec::new();
+        let mut velocity = self.initial_velocity;
+        let mut height = 0.0;
+
+        loop {
+            velocity += self.gravity * timestep;
+            height += velocity * timestep;
+
+            if height <= 0.0 {
+                return heights;
+            }
+            heights.push(height);
+        }
+    }
+}
+
+
+// Enemies are given as distance to the previous enemy and their definition. The player starts
+// on the ground far enough in front of the first enemy to pick any take off point, then
+// every tick it either keeps running, ducks, jumps or continues its jump. The sequence can be
+// cleared if any of these choices survives until the last enemy is behind the player.
+pub fn is_clearable(enemies: &[(f32, &EnemyDefinition)], velocity: f32, arc: &JumpArc) -> bool {
+    // Without movement the enemies never reach the player
+    if enemies.is_empty() || velocity <= 0.0 {
+        return true;
+    }
+
+    let heights = arc.heights(FIXED_TIMESTEP);
+    let lead = arc.jump_length(velocity) + SCALED_TILE_SIZE;
+
+    let mut x = lead;
+    let positions: Vec<(Vec2, Hitbox)> = enemies.iter().enumerate().map(|(index, (gap, definition))| {
+        if index > 0 {
+            x += gap;
+        }
+        (Vec2::new(x, definition.lane_y() - PLAYER_GROUND), definition.hitbox())
+    }).collect();
+
+    // In the first tick the player may keep running or jump
+    let mut first = vec![false; heights.len() + 1];
+    first[0] = true;
+    let first = advance(&first, heights.len());
+
+    // The generator keeps water away from the enemies, so only the enemies are checked
+    survives(&positions, &[], velocity, &heights, first)
+}
+
+
+// Enemies and water are given relative to the player, who is on the ground, enemies with
+// their hitbox and water as the start and end of a stretch. The player has to jump in this
+// tick if staying on the ground leaves no way to clear them.
+pub fn must_jump(enemies: &[(Vec2, Hitbox)], water: &[(f32, f32)], velocity: f32, arc: &JumpArc) -> bool {
+    if (enemies.is_empty() && water.is_empty()) || velocity <= 0.0 {
+        return false;
+    }
+
+    let heights = arc.heights(FIXED_TIMESTEP);
+    let mut running = vec![false; heights.len() + 1];
+    running[0] = true;
+
+    !survives(enemies, water, velocity, &heights, running)
+}
+
+
+// Whether the player has to duck in this tick, to not run into an enemy right in front
+pub fn must_duck(enemies: &[(Vec2, Hitbox)], velocity: f32) -> bool {
+    let running_hitbox = Hitbox::new(&PLAYER_RUNNING_HITBOX);
+    let player = Vec2::new(velocity * FIXED_TIMESTEP, 0.0);
+
+    enemies.iter().any(|(position, hitbox)| running_hitbox.overlaps(player, hitbox, *position))
+}
+
+
+// Phase 0 is on the ground, phase n is the n-th tick of a jump
+fn advance(reachable: &[bool], airtime: usize) -> Vec<bool> {
+    let mut next = vec![false; reachable.len()];
+
+    for phase in (0..reachable.len()).filter(|&phase| reachable[phase]) {
+        if phase == 0 {
+            next[0] = true;
+            if airtime > 0 {
+                next[1] = true;
+            }
+        } else if phase < airtime {
+            next[phase + 1] = true;
+        } else {
+            next[0] = true;
+        }
+    }
+
+    next
+}
+
+
+// Whether any of the phases reachable in the first tick survives every following tick. The
+// player drowns on the ground with its center above water, the coyote time is left as margin.
+fn survives(enemies: &[(Vec2, Hitbox)], water: &[(f32, f32)], velocity: f32, heights: &[f32], first: Vec<bool>) -> bool {
+    let running_hitbox = Hitbox::new(&PLAYER_RUNNING_HITBOX);
+    let ducking_hitbox = Hitbox::new(&PLAYER_DUCKING_HITBOX);
+    let jumping_hitbox = Hitbox::new(&PLAYER_JUMPING_HITBOX);
+
+    // Hitboxes stay within their sprite, so an enemy a sprite width behind is cleared
+    let last_x = enemies.iter().map(|(position, _)| position.x)
+        .chain(water.iter().map(|&(_, end)| end))
+        .fold(f32::MIN, f32::max);
+    let step = velocity * FIXED_TIMESTEP;
+    let ticks = ((last_x + SCALED_TILE_SIZE) / step).ceil().max(1.0) as usize;
+
+    let mut reachable = first;
+
+    for tick in 1..=ticks {
+        if tick > 1 {
+            reachable = advance(&reachable, heights.len());
+        }
+
+        let offset = step * tick as f32;
+
+        let hits = |hitbox: &Hitbox, player: Vec2| {
+            enemies.iter().any(|(enemy, enemy_hitbox)| hitbox.overlaps(player, enemy_hitbox, *enemy))
+        };
+
+        for (phase, alive) in reachable.iter_mut().enumerate() {
+            // On the ground the player can run or duck in every tick
+            let hit = if phase == 0 {
+                let player = Vec2::new(offset, 0.0);
+                let drowns = water.iter().any(|&(start, end)| (start..end).contains(&offset));
+                drowns || (hits(&running_hitbox, player) && hits(&ducking_hitbox, player))
+            } else {
+                hits(&jumping_hitbox, Vec2::new(offset, heights[phase - 1]))
+            };
+
+            if hit {
+                *alive = false;
+            }
+        }
+
+        if !reachable.contains(&true) {
+            return false;
+        }
+    }
+
+    true
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemy_definitions::EnemyDefinitions;
+
+    fn enemy(name: &str) -> EnemyDefinition {
+        let definitions = EnemyDefinitions::parse(include_bytes!("../assets/enemies/default.enemies.ron")).unwrap();
+        definitions.0.into_iter().find(|definition| definition.name == name).unwrap()
+    }
+
+    #[test]
+    fn airtime_and_jump_length_follow_the_arc() {
+        let arc = JumpArc::default();
+
+        assert!((arc.airtime() - 1000.0 / 980.0).abs() < 1e-4);
+        assert!((arc.jump_length(200.0) - 200.0 * 1000.0 / 980.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn empty_sequence_is_clearable() {
+        assert!(is_clearable(&[], INITIAL_VELOCITY, &JumpArc::default()));
+    }
+
+    #[test]
+    fn standing_world_is_clearable() {
+        let enemies = [(0.0, &enemy("Lion")), (0.0, &enemy("Crocodile"))];
+
+        assert!(is_clearable(&enemies, 0.0, &JumpArc::default()));
+    }
+
+    #[test]
+    fn single_ground_enemy_is_clearable() {
+        assert!(is_clearable(&[(0.0, &enemy("Lion"))], INITIAL_VELOCITY, &JumpArc::default()));
+        assert!(is_clearable(&[(0.0, &enemy("Crocodile"))], MAXIMUM_VELOCITY, &JumpArc::default()));
+    }
+
+    #[test]
+    fn eagle_is_cleared_by_ducking() {
+        let enemies = [(0.0, &enemy("Eagle")), (0.0, &enemy("Eagle"))];
+
+        assert!(is_clearable(&enemies, INITIAL_VELOCITY, &JumpArc::default()));
+    }
+
+    #[test]
+    fn ground_enemies_need_room_to_land() {
+        let too_close = [(0.0, &enemy("Lion")), (100.0, &enemy("Crocodile"))];
+        let minimum_space = [(0.0, &enemy("Lion")), (ENEMY_MINIMUM_SPACE, &enemy("Crocodile"))];
+
+        assert!(!is_clearable(&too_close, INITIAL_VELOCITY, &JumpArc::default()));
+        assert!(is_clearable(&minimum_space, INITIAL_VELOCITY, &JumpArc::default()));
+    }
+
+    // Halfway to the maximum velocity one jump clears both enemies up to a gap of 156, landing
+    // in between to jump again takes a gap of over 220
+    #[test]
+    fn ground_enemies_close_together_are_cleared_with_one_jump() {
+        let velocity = (INITIAL_VELOCITY + MAXIMUM_VELOCITY) / 2.0;
+        let one_jump = [(0.0, &enemy("Lion")), (156.0, &enemy("Crocodile"))];
+        let slightly_wider = [(0.0, &enemy("Lion")), (160.0, &enemy("Crocodile"))];
+
+        assert!(is_clearable(&one_jump, velocity, &JumpArc::default()));
+        assert!(!is_clearable(&slightly_wider, velocity, &JumpArc::default()));
+    }
+
+    #[test]
+    fn eagle_right_behind_ground_enemy_is_not_clearable() {
+        let enemies = [(0.0, &enemy("Lion")), (30.0, &enemy("Eagle"))];
+
+        assert!(!is_clearable(&enemies, INITIAL_VELOCITY, &JumpArc::default()));
+    }
+
+    #[test]
+    fn overlapping_enemies_are_not_clearable() {
+        let enemies = [(0.0, &enemy("Lion")), (0.0, &enemy("Eagle"))];
+
+        assert!(!is_clearable(&enemies, INITIAL_VELOCITY, &JumpArc::default()));
+    }
+
+    #[test]
+    fn weak_jump_can_not_clear_a_ground_enemy() {
+        let arc = JumpArc { initial_velocity: 100.0, gravity: JUMP_GRAVITY };
+
+        assert!(!is_clearable(&[(0.0, &enemy("Lion"))], INITIAL_VELOCITY, &arc));
+    }
+
+    #[test]
+    fn jump_is_needed_right_before_a_ground_enemy() {
+        let close = [(Vec2::new(100.0, 0.0), enemy("Lion").hitbox())];
+        let far = [(Vec2::new(4.0 * SCALED_TILE_SIZE, 0.0), enemy("Lion").hitbox())];
+
+        assert!(must_jump(&close, &[], INITIAL_VELOCITY, &JumpArc::default()));
+        assert!(!must_jump(&far, &[], INITIAL_VELOCITY, &JumpArc::default()));
+    }
+
+    #[test]
+    fn water_is_jumped_at_the_last_moment() {
+        let close = [(1.0, 1.0 + SCALED_TILE_SIZE)];
+        let far = [(2.0 * SCALED_TILE_SIZE, 3.0 * SCALED_TILE_SIZE)];
+
+        assert!(must_jump(&[], &close, INITIAL_VELOCITY, &JumpArc::default()));
+        assert!(!must_jump(&[], &far, INITIAL_VELOCITY, &JumpArc::default()));
+    }
+
+    #[test]
+    fn water_longer_than_a_jump_can_not_be_cleared() {
+        let arc = JumpArc::default();
+        let length = arc.jump_length(INITIAL_VELOCITY);
+        let heights = arc.heights(FIXED_TIMESTEP);
+        let mut running = vec![false; heights.len() + 1];
+        running[0] = true;
+
+        assert!(!survives(&[], &[(SCALED_TILE_SIZE, SCALED_TILE_SIZE + length + 10.0)], INITIAL_VELOCITY, &heights, running.clone()));
+        assert!(survives(&[], &[(SCALED_TILE_SIZE, 2.0 * SCALED_TILE_SIZE)], INITIAL_VELOCITY, &heights, running));
+    }
+
+    #[test]
+    fn eagle_is_ducked_under_instead_of_jumped() {
+        let close = [(Vec2::new(70.0, ENEMY_FLYING_Y - PLAYER_GROUND), enemy("Eagle").hitbox())];
+        let far = [(Vec2::new(SCALED_TILE_SIZE, ENEMY_FLYING_Y - PLAYER_GROUND), enemy("Eagle").hitbox())];
+
+        assert!(!must_jump(&close, &[], INITIAL_VELOCITY, &JumpArc::default()));
+        assert!(must_duck(&close, INITIAL_VELOCITY));
+        assert!(!must_duck(&far, INITIAL_VELOCITY));
+    }
+
+    #[test]
+    fn eagle_can_not_be_jumped_over() {
+        let eagle = enemy("Eagle").hitbox();
+        let jumping = Hitbox::new(&PLAYER_JUMPING_HITBOX);
+        let apex = JumpArc::default().heights(FIXED_TIMESTEP).into_iter().fold(0.0, f32::max);
+
+        assert!(jumping.overlaps(Vec2::new(0.0, apex), &eagle, Vec2::new(0.0, ENEMY_FLYING_Y - PLAYER_GROUND)));
+    }
+
+    #[test]
+    fn running_player_hits_an_eagle_but_a_ducking_one_does_not() {
+        let eagle = enemy("Eagle").hitbox();
+        let position = Vec2::new(0.0, ENEMY_FLYING_Y - PLAYER_GROUND);
+
+        assert!(Hitbox::new(&PLAYER_RUNNING_HITBOX).overlaps(Vec2::ZERO, &eagle, position));
+        assert!(!Hitbox::new(&PLAYER_DUCKING_HITBOX).overlaps(Vec2::ZERO, &eagle, position));
+    }
+}
@@ -0,0 +1,382 @@
+//! Enemy spawn tables
+//!
+//! A spawn table gives every enemy a weight which changes with the distance run, so new enemies
+//! can be introduced later in a run. The enemies come in patterns like pairs or hops, which
+//! are weighted the same way. Each difficulty has its own table, the default one is
+//! `assets/enemies/normal.spawns.ron`.
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+use rand::Rng;
+use rand::distr::Distribution;
+use rand::distr::weighted::WeightedIndex;
+use serde::Deserialize;
+
+use crate::enemy_definitions::EnemyDefinition;
+
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SpawnWeight {
+    pub from: f32,
+    pub weight: f32,
+}
+
+
+// Weights by distance, sorted by distance. The enemy is not spawned before the first one,
+// between two the weight changes linearly and after the last it stays.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct SpawnCurve(pub Vec<SpawnWeight>);
+
+
+impl SpawnCurve {
+    pub fn weight(&self, distance: f32) -> f32 {
+        let Some(next) = self.0.iter().position(|step| step.from > distance) else {
+            return self.0.last().map_or(0., |step| step.weight);
+        };
+        let Some(previous) = next.checked_sub(1).map(|index| self.0[index]) else {
+            return 0.;
+        };
+
+        let step = self.0[next];
+        let t = (distance - previous.from) / (step.from - previous.from);
+        previous.weight + (step.weight - previous.weight) * t
+    }
+}
+
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct PatternEnemy {
+    // Picked by the spawn weights when not given
+    #[serde(default)]
+    pub enemy: Option<String>,
+    // Distance to the first enemy of the pattern at the initial velocity
+    pub offset: f32,
+}
+
+
+// Enemies placed together, the generator chains patterns with the usual spacing between them
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct EnemyPattern {
+    pub name: String,
+    pub weight: SpawnCurve,
+    // Extra space in front of the pattern at the initial velocity
+    #[serde(default)]
+    pub rest: f32,
+    pub enemies: Vec<PatternEnemy>,
+}
+
+
+// The spawn curves by enemy name and the patterns
+#[derive(Asset, TypePath, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpawnTable {
+    pub enemies: HashMap<String, SpawnCurve>,
+    pub patterns: Vec<EnemyPattern>,
+}
+
+
+impl SpawnTable {
+    pub fn parse(bytes: &[u8]) -> Result<Self, BevyError> {
+        let table: Self = ron::de::from_bytes(bytes)?;
+        table.validate()?;
+        Ok(table)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let curves = self.enemies.iter().chain(self.patterns.iter().map(|pattern| (&pattern.name, &pattern.weight)));
+
+        for (name, curve) in curves {
+            if curve.0.iter().any(|step| step.weight < 0.0) {
+                return Err(invalid(format!("{} has a negative spawn weight", name)));
+            }
+            if curve.0.windows(2).any(|steps| steps[0].from >= steps[1].from) {
+                return Err(invalid(format!("the spawn weights of {} are not sorted by distance", name)));
+            }
+        }
+
+        for pattern in &self.patterns {
+            if pattern.enemies.first().is_none_or(|enemy| enemy.offset != 0.0) {
+                return Err(invalid(format!("pattern {} has to start with an enemy at offset 0", pattern.name)));
+            }
+            if pattern.enemies.windows(2).any(|enemies| enemies[0].offset >= enemies[1].offset) {
+                return Err(invalid(format!("the enemies of pattern {} are not sorted by offset", pattern.name)));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The curves in the order of the definitions, an enemy missing from the table is never
+    // spawned. Every run starts with an enemy and goes on forever, so an enemy and a pattern
+    // have to be allowed at every distance.
+    pub fn plan(&self, definitions: &[EnemyDefinition]) -> Result<SpawnPlan, BevyError> {
+        let kind = |name: &String| definitions.iter()
+            .position(|definition| &definition.name == name)
+            .ok_or_else(|| invalid(format!("the spawn table has an unknown enemy {}", name)));
+
+        for name in self.enemies.keys() {
+            kind(name)?;
+        }
+
+        let mut patterns = Vec::new();
+        for pattern in &self.patterns {
+            let mut enemies = Vec::new();
+            for enemy in &pattern.enemies {
+                enemies.push((enemy.enemy.as_ref().map(kind).transpose()?, enemy.offset));
+            }
+            patterns.push(PlannedPattern { weight: pattern.weight.clone(), rest: pattern.rest, enemies });
+        }
+
+        let plan = SpawnPlan {
+            enemies: definitions.iter()
+                .map(|definition| self.enemies.get(&definition.name).cloned().unwrap_or_default())
+                .collect(),
+            patterns,
+        };
+
+        // The weights change linearly between the distances of the steps, they are positive
+        // everywhere when they are at each of them
+        let distances = std::iter::once(0.).chain(
+            plan.enemies.iter().chain(plan.patterns.iter().map(|pattern| &pattern.weight))
+                .flat_map(|curve| curve.0.iter().map(|step| step.from))
+        );
+        for distance in distances {
+            if plan.enemies.iter().all(|curve| curve.weight(distance) <= 0.) {
+                return Err(invalid(format!("no enemy can be spawned at {}", distance)).into());
+            }
+            if plan.patterns.iter().all(|pattern| plan.pattern_weight(pattern, distance) <= 0.) {
+                return Err(invalid(format!("no pattern can be spawned at {}", distance)).into());
+            }
+        }
+
+        Ok(plan)
+    }
+}
+
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+
+// A pattern with its enemies as index into the definitions, None is picked by the spawn weights
+#[derive(Debug, Clone)]
+pub struct PlannedPattern {
+    weight: SpawnCurve,
+    pub rest: f32,
+    pub enemies: Vec<(Option<usize>, f32)>,
+}
+
+
+// The spawn table resolved against the enemy definitions
+#[derive(Debug, Clone)]
+pub struct SpawnPlan {
+    // In the order of the definitions
+    pub enemies: Vec<SpawnCurve>,
+    pub patterns: Vec<PlannedPattern>,
+}
+
+
+impl SpawnPlan {
+    // A pattern is only allowed once all the enemies named in it are
+    fn pattern_weight(&self, pattern: &PlannedPattern, distance: f32) -> f32 {
+        let enemies_allowed = pattern.enemies.iter()
+            .filter_map(|(kind, _)| *kind)
+            .all(|kind| self.enemies[kind].weight(distance) > 0.);
+
+        if enemies_allowed { pattern.weight.weight(distance) } else { 0. }
+    }
+
+    // Picks an enemy by its weight at the distance, None if no enemy is allowed there
+    pub fn choose_enemy(&self, rng: &mut impl Rng, distance: f32) -> Option<usize> {
+        choose_by_weight(rng, self.enemies.iter().map(|curve| curve.weight(distance)))
+    }
+
+    pub fn choose_pattern(&self, rng: &mut impl Rng, distance: f32) -> Option<&PlannedPattern> {
+        choose_by_weight(rng, self.patterns.iter().map(|pattern| self.pattern_weight(pattern, distance)))
+            .map(|index| &self.patterns[index])
+    }
+}
+
+
+fn choose_by_weight(rng: &mut impl Rng, weights: impl Iterator<Item = f32>) -> Option<usize> {
+    WeightedIndex::new(weights)
+        .ok()
+        .map(|weights| weights.sample(rng))
+}
+
+
+#[derive(Default)]
+pub struct SpawnTableLoader;
+
+
+impl AssetLoader for SpawnTableLoader {
+    type Asset = SpawnTable;
+    type Settings = ();
+    type Error = BevyError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<SpawnTable, BevyError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        SpawnTable::parse(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["spawns.ron"]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::enemy_definitions::EnemyDefinitions;
+    use crate::model::DifficultyCurve;
+    use crate::solvability::{JumpArc, is_clearable};
+
+    const SAMPLES: usize = 20_000;
+
+    fn definitions() -> EnemyDefinitions {
+        EnemyDefinitions::parse(include_bytes!("../assets/enemies/default.enemies.ron")).unwrap()
+    }
+
+    // A table with the enemy weights and a single enemy as the only pattern
+    fn table(enemies: &str) -> SpawnTable {
+        let table = format!(r#"(
+            enemies: {},
+            patterns: [(name: "Single", weight: [(from: 0.0, weight: 1.0)], enemies: [(offset: 0.0)])],
+        )"#, enemies);
+        SpawnTable::parse(table.as_bytes()).unwrap()
+    }
+
+    // Share of each enemy in many picks at the distance
+    fn shares(plan: &SpawnPlan, distance: f32) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = vec![0; plan.enemies.len()];
+
+        for _ in 0..SAMPLES {
+            counts[plan.choose_enemy(&mut rng, distance).unwrap()] += 1;
+        }
+
+        counts.iter().map(|&count| count as f32 / SAMPLES as f32).collect()
+    }
+
+    #[test]
+    fn weights_change_linearly_between_distances() {
+        let curve = SpawnCurve(vec![
+            SpawnWeight { from: 100., weight: 1. },
+            SpawnWeight { from: 300., weight: 3. },
+        ]);
+
+        assert_eq!(curve.weight(99.), 0.);
+        assert_eq!(curve.weight(100.), 1.);
+        assert_eq!(curve.weight(200.), 2.);
+        assert_eq!(curve.weight(1000.), 3.);
+    }
+
+    #[test]
+    fn picks_follow_the_weights_at_the_distance() {
+        let table = SpawnTable::parse(include_bytes!("../assets/enemies/normal.spawns.ron")).unwrap();
+        let plan = table.plan(&definitions().0).unwrap();
+
+        for distance in [0., 250., 499., 500., 1000., 5000.] {
+            let total: f32 = plan.enemies.iter().map(|curve| curve.weight(distance)).sum();
+
+            for (share, curve) in shares(&plan, distance).iter().zip(&plan.enemies) {
+                let expected = curve.weight(distance) / total;
+                // Several standard deviations of the share at this sample size
+                assert!((share - expected).abs() < 0.02, "share {} instead of {} at {}", share, expected, distance);
+                if expected == 0. {
+                    assert_eq!(*share, 0., "an enemy not allowed at {} was picked", distance);
+                }
+            }
+        }
+    }
+
+    // Every pattern of the bundled tables with any enemy in its open places, right after the
+    // shortest spacing and at the slowest and fastest velocity
+    #[test]
+    fn bundled_patterns_can_be_cleared() {
+        let definitions = definitions();
+        let curve = DifficultyCurve::default();
+        let arc = JumpArc::default();
+        let tables: [&[u8]; 2] = [
+            include_bytes!("../assets/enemies/normal.spawns.ron"),
+            include_bytes!("../assets/enemies/hard.spawns.ron"),
+        ];
+
+        for table in tables {
+            let table = SpawnTable::parse(table).unwrap();
+            let plan = table.plan(&definitions.0).unwrap();
+
+            for (pattern, planned) in table.patterns.iter().zip(&plan.patterns) {
+                for velocity in [curve.initial_velocity, curve.max_velocity] {
+                    let scale = curve.spacing_scale(velocity);
+                    let open = planned.enemies.iter().filter(|(kind, _)| kind.is_none()).count() as u32;
+
+                    for choice in 0..definitions.0.len().pow(open) {
+                        let mut choice = choice;
+                        let mut previous_offset = 0.;
+                        let sequence: Vec<(f32, &EnemyDefinition)> = planned.enemies.iter().map(|&(kind, offset)| {
+                            let kind = kind.unwrap_or_else(|| {
+                                let kind = choice % definitions.0.len();
+                                choice /= definitions.0.len();
+                                kind
+                            });
+                            let gap = (offset - previous_offset) * scale;
+                            previous_offset = offset;
+                            (gap, &definitions.0[kind])
+                        }).collect();
+
+                        assert!(is_clearable(&sequence, velocity, &arc), "{} can not be cleared at {}", pattern.name, velocity);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tables_without_an_enemy_somewhere_are_rejected() {
+        let definitions = definitions();
+        let late_only = table(r#"{ "Lion": [(from: 500.0, weight: 1.0)] }"#);
+        let gap = table(r#"{
+            "Lion": [(from: 0.0, weight: 1.0), (from: 500.0, weight: 0.0)],
+            "Eagle": [(from: 1000.0, weight: 1.0)],
+        }"#);
+        let unknown = table(r#"{ "Lion": [(from: 0.0, weight: 1.0)], "Snake": [(from: 0.0, weight: 1.0)] }"#);
+
+        assert!(late_only.plan(&definitions.0).is_err());
+        assert!(gap.plan(&definitions.0).is_err());
+        assert!(unknown.plan(&definitions.0).is_err());
+        assert!(table(r#"{ "Lion": [(from: 0.0, weight: 1.0)] }"#).plan(&definitions.0).is_ok());
+    }
+
+    #[test]
+    fn patterns_wait_for_the_enemies_they_name() {
+        let table = SpawnTable::parse(br#"(
+            enemies: { "Lion": [(from: 0.0, weight: 1.0)], "Eagle": [(from: 1000.0, weight: 1.0)] },
+            patterns: [
+                (name: "Single", weight: [(from: 0.0, weight: 1.0)], enemies: [(offset: 0.0)]),
+                (name: "Eagle pair", weight: [(from: 0.0, weight: 1.0)], enemies: [(enemy: Some("Eagle"), offset: 0.0), (enemy: Some("Eagle"), offset: 300.0)]),
+            ],
+        )"#).unwrap();
+        let plan = table.plan(&definitions().0).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let early = (0..100).filter(|_| plan.choose_pattern(&mut rng, 500.).unwrap().enemies.len() == 2).count();
+        let late = (0..100).filter(|_| plan.choose_pattern(&mut rng, 1500.).unwrap().enemies.len() == 2).count();
+
+        assert_eq!(early, 0);
+        assert!(late > 0);
+    }
+}